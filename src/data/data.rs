@@ -1,19 +1,71 @@
 
+use std::cmp::Ordering;
 use std::fmt::{Display, Formatter};
 
 use denest::*;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SymStr {
     Symbol(Box<str>),
     String(Box<str>),
+    Int(i64),
+    Float(f64),
+}
+
+impl PartialEq for SymStr {
+    fn eq(&self, other : &Self) -> bool {
+        matches!(self.partial_cmp(other), Some(Ordering::Equal))
+    }
+}
+
+// Note:  converting the int to a float loses precision past 2^53, which would make 
+// equality intransitive, so the float is split into whole and fractional parts instead.  
+// The int is an i128 so that both i64 and u64 values fit.
+pub (crate) fn compare_int_float(int : i128, float : f64) -> Option<Ordering> {
+    // Note:  2^127 is exact as a float and is one past the largest i128
+    const LIMIT : f64 = 170141183460469231731687303715884105728.0;
+
+    if float.is_nan() {
+        None
+    }
+    else if float >= LIMIT {
+        Some(Ordering::Less)
+    }
+    else if float < -LIMIT {
+        Some(Ordering::Greater)
+    }
+    else {
+        let whole = float.trunc() as i128;
+        match int.cmp(&whole) {
+            Ordering::Equal => 0.0.partial_cmp(&float.fract()),
+            ordering => Some(ordering),
+        }
+    }
+}
+
+impl PartialOrd for SymStr {
+    fn partial_cmp(&self, other : &Self) -> Option<Ordering> {
+        use SymStr::*;
+        match (self, other) {
+            (Symbol(a), Symbol(b)) => a.partial_cmp(b),
+            (String(a), String(b)) => a.partial_cmp(b),
+            (Int(a), Int(b)) => a.partial_cmp(b),
+            (Float(a), Float(b)) => a.partial_cmp(b),
+            (Int(a), Float(b)) => compare_int_float(i128::from(*a), *b),
+            (Float(a), Int(b)) => compare_int_float(i128::from(*b), *a).map(Ordering::reverse),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Data {
     SymStr(SymStr), 
     Cons { name: Box<str>, params: Vec<Data> },
     List(Vec<Data>),
+    Record(Vec<(Box<str>, Data)>),
 }
 
 impl<'a> Linearizable<'a> for Data {
@@ -22,6 +74,59 @@ impl<'a> Linearizable<'a> for Data {
             Data::SymStr(_) => vec![],
             Data::Cons { params, .. } => params.iter().collect(),
             Data::List(ds) => ds.iter().collect(),
+            Data::Record(fs) => fs.iter().map(|(_, d)| d).collect(),
+        }
+    }
+}
+
+fn escape(s : &str) -> String {
+    let mut output = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            '\0' => output.push_str("\\0"),
+            c if c.is_control() => output.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => output.push(c),
+        }
+    }
+    output
+}
+
+fn is_word(s : &str) -> bool {
+    let mut cs = s.chars();
+    match cs.next() {
+        Some(c) if c.is_alphabetic() || c == '_' => cs.all(|c| c.is_alphabetic() || c.is_ascii_digit() || c == '_'),
+        _ => false,
+    }
+}
+
+/// Displays a symbol, constructor, or field name so that it parses back, quoting it 
+/// with backticks when it isn't a plain word.
+pub (crate) struct Name<'a>(pub &'a str);
+
+impl<'a> Display for Name<'a> {
+    fn fmt(&self, f : &mut Formatter) -> std::fmt::Result {
+        if is_word(self.0) {
+            write!(f, "{}", self.0)
+        }
+        else {
+            write!(f, "`{}`", self.0.replace('\\', "\\\\").replace('`', "\\`"))
+        }
+    }
+}
+
+impl Display for SymStr {
+    fn fmt(&self, f : &mut Formatter) -> std::fmt::Result {
+        match self {
+            SymStr::String(s) => write!(f, "\"{}\"", escape(s)), 
+            SymStr::Symbol(s) => write!(f, ":{}", Name(s)),
+            SymStr::Int(i) => write!(f, "{}", i),
+            // Note:  the parser rejects floats that aren't finite, so NaN and the infinities don't round trip
+            SymStr::Float(x) => write!(f, "{:?}", x),
         }
     }
 }
@@ -29,10 +134,10 @@ impl<'a> Linearizable<'a> for Data {
 impl Display for Data {
     fn fmt(&self, f : &mut Formatter) -> std::fmt::Result {
         match self {
-            Data::SymStr(SymStr::String(s)) => write!(f, "\"{}\"", s), 
-            Data::SymStr(SymStr::Symbol(s)) => write!(f, ":{}", s),
-            Data::Cons { name, params } => write!(f, "{}({})", name, params.iter().map(|x| format!("{}", x)).collect::<Vec<_>>().join(", ")),
+            Data::SymStr(s) => write!(f, "{}", s), 
+            Data::Cons { name, params } => write!(f, "{}({})", Name(name), params.iter().map(|x| format!("{}", x)).collect::<Vec<_>>().join(", ")),
             Data::List(ds) => write!(f, "[{}]", ds.iter().map(|x| format!("{}", x)).collect::<Vec<_>>().join(", ")),
+            Data::Record(fs) => write!(f, "{{{}}}", fs.iter().map(|(k, x)| format!("{}: {}", Name(k), x)).collect::<Vec<_>>().join(", ")),
         }
     }
 }
@@ -55,6 +160,18 @@ impl From<String> for Data {
     }
 }
 
+impl From<i64> for Data {
+    fn from(item : i64) -> Self {
+        Data::SymStr(SymStr::Int(item))
+    }
+}
+
+impl From<f64> for Data {
+    fn from(item : f64) -> Self {
+        Data::SymStr(SymStr::Float(item))
+    }
+}
+
 impl<T> From<Vec<T>> for Data where Data : From<T> {
     fn from(item : Vec<T>) -> Self {
         Data::List(item.into_iter().map(|x| x.into()).collect::<Vec<Data>>())
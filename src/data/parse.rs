@@ -5,36 +5,21 @@ use renounce::*;
 use crate::parsing::*;
 use super::data::*;
 
-#[derive(Debug)]
-pub struct E(Box<str>);
-
-impl std::fmt::Display for E {
-    fn fmt(&self, f : &mut std::fmt::Formatter) -> std::fmt::Result {
-        match self {
-            E(s) => write!(f, "{}", s),
-        }
-    }
-}
-
-impl std::error::Error for E { }
+pub use crate::error::SyntaxError;
 
 impl std::str::FromStr for Data {
-    type Err = Box<dyn std::error::Error>;  
+    type Err = crate::error::ParseError;
 
     fn from_str(s : &str) -> Result<Self, Self::Err> {
-        let mut cs = s.chars();
-        let result = parse(&mut cs);
-        match result {
-            Ok(v) => Ok(v),
-            Err(ParseError::Fatal(x)) => Err(Box::new(E(format!("Error Trace: {:?}\nAt: {}", x, cs.collect::<String>()).into()))),
-            Err(ParseError::Error) => Err(Box::new(E("Not Data".into()))),
-        }
+        run_parser(s, parse, "data")
     }
 }
 
+expecting!(Unexpected, expect_data = parse_data : Data => "data");
+
 fn parse(input : &mut Chars) -> Result<Data, ParseError> {
     parser!(input => {
-        data <= ! parse_data;
+        data <= ! expect_data;
         ! end;
         select data
     })
@@ -44,8 +29,10 @@ fn parse_data(input : &mut Chars) -> Result<Data, ParseError> {
     fn options(input : &mut Chars) -> Result<Data, ParseError> {
         alt!(input => parse_cons; 
                       parse_list; 
+                      parse_record;
                       parse_symbol;
-                      parse_string_data)
+                      parse_string_data;
+                      parse_number_data)
     }
 
     parser!(input => {
@@ -61,11 +48,11 @@ fn parse_cons(input : &mut Chars) -> Result<Data, ParseError> {
     pat!(parse_r_paren: char => () = ')' => ());
 
     fn param_list(input : &mut Chars) -> Result<Vec<Data>, ParseError> {
-        parse_list!(input => parse_l_paren, parse_data : Data, parse_r_paren)
+        parse_list!(input => parse_l_paren, parse_data : Data, parse_r_paren = "`)`")
     }
 
     parser!(input => {
-        cons_name <= parse_word;
+        cons_name <= parse_name;
         _clear <= parse_whitespace;
         params <= param_list;
         select Data::Cons { name: cons_name, params }
@@ -76,7 +63,7 @@ fn parse_symbol(input : &mut Chars) -> Result<Data, ParseError> {
     pat!(parse_colon: char => () = ':' => ());
     parser!(input => {
         _colon <= parse_colon;
-        word <= parse_word;
+        word <= parse_name;
         select Data::SymStr(SymStr::Symbol(word))
     })
 }
@@ -88,11 +75,61 @@ fn parse_string_data(input : &mut Chars) -> Result<Data, ParseError> {
     })
 }
 
+fn parse_number_data(input : &mut Chars) -> Result<Data, ParseError> {
+    parser!(input => {
+        number <= parse_number;
+        select Data::SymStr(number)
+    })
+}
+
 fn parse_list(input : &mut Chars) -> Result<Data, ParseError> {
     pat!(parse_l_square: char => () = '[' => ());
     pat!(parse_r_square: char => () = ']' => ());
 
-    Ok(Data::List(parse_list!(input => parse_l_square, parse_data : Data, parse_r_square)?))
+    Ok(Data::List(parse_list!(input => parse_l_square, parse_data : Data, parse_r_square = "`]`")?))
+}
+
+fn parse_record(input : &mut Chars) -> Result<Data, ParseError> {
+    pat!(parse_l_curl: char => () = '{' => ());
+    pat!(parse_r_curl: char => () = '}' => ());
+    pat!(parse_colon: char => () = ':' => ());
+
+    expecting!(Unexpected, expect_colon = parse_colon : () => "`:`");
+
+    fn duplicate_key(_input : &mut Chars) -> Result<(), ParseError> {
+        Err(ParseError::Error)
+    }
+
+    expecting!(Unexpected, expect_new_key = duplicate_key : () => "a key that isn't already in the record");
+
+    // Note:  the field keeps how much input was left at its key so that a duplicate key can be reported there
+    fn parse_field(input : &mut Chars) -> Result<(usize, Box<str>, Data), ParseError> {
+        let rest = input.as_str().len();
+        parser!(input => {
+            key <= parse_name;
+            _clear <= parse_whitespace;
+            _colon <= ! expect_colon;
+            data <= ! expect_data;
+            select (rest, key, data)
+        })
+    }
+
+    let start = input.clone();
+    let fields = parse_list!(input => parse_l_curl, parse_field : (usize, Box<str>, Data), parse_r_curl = "`}`")?;
+
+    let duplicate = fields.iter().enumerate().find(|(i, (_, key, _))| fields[..*i].iter().any(|(_, k, _)| k == key));
+    if let Some((_, (rest, _, _))) = duplicate {
+        *input = start;
+        while input.as_str().len() > *rest {
+            input.next();
+        }
+        parser!(input => {
+            _key <= ! expect_new_key;
+            select ()
+        })?;
+    }
+
+    Ok(Data::Record(fields.into_iter().map(|(_, key, data)| (key, data)).collect()))
 }
 
 #[cfg(test)]
@@ -102,6 +139,38 @@ mod test {
 
     fn slice<'a, T>(input : &'a Vec<T>) -> &'a [T] { &input[..] }
 
+    #[test]
+    fn should_compare_int_and_float_exactly() {
+        use std::cmp::Ordering;
+
+        let big = 1i64 << 53;
+        assert_eq!(SymStr::Int(big), SymStr::Float(big as f64));
+        assert_ne!(SymStr::Int(big + 1), SymStr::Float(big as f64));
+        assert_eq!(SymStr::Int(big + 1).partial_cmp(&SymStr::Float(big as f64)), Some(Ordering::Greater));
+        assert_eq!(SymStr::Int(2).partial_cmp(&SymStr::Float(2.5)), Some(Ordering::Less));
+        assert_eq!(SymStr::Int(-2).partial_cmp(&SymStr::Float(-2.5)), Some(Ordering::Greater));
+        assert_eq!(SymStr::Float(-2.5).partial_cmp(&SymStr::Int(-2)), Some(Ordering::Less));
+        assert_eq!(SymStr::Int(i64::MAX).partial_cmp(&SymStr::Float(9223372036854775808.0)), Some(Ordering::Less));
+        assert_eq!(SymStr::Int(i64::MIN), SymStr::Float(-9223372036854775808.0));
+        assert_eq!(SymStr::Int(0).partial_cmp(&SymStr::Float(f64::NAN)), None);
+        assert_eq!(SymStr::Int(3), SymStr::Float(3.0));
+    }
+
+    #[test]
+    fn should_reject_floats_that_are_not_finite() {
+        assert!("1e999".parse::<Data>().is_err());
+        assert!("-1e999".parse::<Data>().is_err());
+        assert!("1e300".parse::<Data>().is_ok());
+    }
+
+    #[test]
+    fn should_parse_underscore_symbol() {
+        let data = ":_".parse::<Data>().unwrap();
+        assert_eq!(data, Data::SymStr(SymStr::Symbol("_".into())));
+        assert_eq!(format!("{}", data), ":_");
+        assert_eq!(":`_`".parse::<Data>().unwrap(), data);
+    }
+
     #[test]
     fn should_parse_complex_data() {
         let input = " name  (  other ( one( :a, :b, num([:c, :d, :e, [:blarg]]) ) ) , :inner  )";
@@ -146,6 +215,143 @@ mod test {
         assert!(matched);
     }
 
+    #[test]
+    fn should_parse_numbers() {
+        let input = " [ 5, -3, 5.5, 1e-3 ] ";
+        let data = input.parse::<Data>().unwrap();
+
+        let mut matched = false;
+        atom!(data => [Data::List(ref params)] params; 
+              slice $ [ [Data::SymStr(SymStr::Int(a)), Data::SymStr(SymStr::Int(b)), Data::SymStr(SymStr::Float(c)), Data::SymStr(SymStr::Float(d))] ] => { 
+            assert_eq!(*a, 5);
+            assert_eq!(*b, -3);
+            assert_eq!(*c, 5.5);
+            assert_eq!(*d, 0.001);
+            matched = true;
+        } );
+        assert!(matched);
+    }
+
+    #[test]
+    fn should_display_numbers_so_they_parse_back() {
+        let input = "cons(5, -3, 5.0, 0.25)";
+        let data = input.parse::<Data>().unwrap();
+        let output = format!("{}", data);
+        assert_eq!(output, "cons(5, -3, 5.0, 0.25)");
+        assert!(matches!(output.parse::<Data>().unwrap(), Data::Cons { params, .. } if matches!(params[2], Data::SymStr(SymStr::Float(_)))));
+    }
+
+    #[test]
+    fn should_display_strings_so_they_parse_back() {
+        let data = Data::List(vec![ Data::SymStr(SymStr::String("say \"hi\"".into()))
+                                  , Data::SymStr(SymStr::String("back\\slash".into()))
+                                  , Data::SymStr(SymStr::String("\n\r\t\0".into()))
+                                  , Data::SymStr(SymStr::String("\x07\x1b\u{85}".into()))
+                                  , Data::SymStr(SymStr::String("caf\u{e9} \u{1F600}".into()))
+                                  ]);
+        let output = format!("{}", data);
+        assert_eq!(output, "[\"say \\\"hi\\\"\", \"back\\\\slash\", \"\\n\\r\\t\\0\", \"\\u{7}\\u{1b}\\u{85}\", \"caf\u{e9} \u{1F600}\"]");
+        assert_eq!(output.parse::<Data>().unwrap(), data);
+    }
+
+    #[test]
+    fn should_parse_record() {
+        let input = " { name: :alice, age : 30, tags: [:a, :b], } ";
+        let data = input.parse::<Data>().unwrap();
+
+        let mut matched = false;
+        atom!(data => [Data::Record(ref fields)] fields; 
+              slice $ [ [(name, Data::SymStr(SymStr::Symbol(alice))), (age, Data::SymStr(SymStr::Int(30))), (tags, Data::List(ts))] ] => { 
+            assert_eq!(**name, *"name");
+            assert_eq!(**alice, *"alice");
+            assert_eq!(**age, *"age");
+            assert_eq!(**tags, *"tags");
+            assert_eq!(ts.len(), 2);
+            matched = true;
+        } );
+        assert!(matched);
+    }
+
+    #[test]
+    fn should_display_record_so_it_parses_back() {
+        let input = "cons({name: :alice, inner: {}, items: [1, \"x\"]})";
+        let data = input.parse::<Data>().unwrap();
+        let output = format!("{}", data);
+        assert_eq!(output, input);
+        assert_eq!(output.parse::<Data>().unwrap(), data);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn should_round_trip_through_serde() {
+        let input = "cons(:a, \"b\", [1, 2.5], { key: :value })";
+        let data = input.parse::<Data>().unwrap();
+        let json = serde_json::to_string(&data).unwrap();
+        let output : Data = serde_json::from_str(&json).unwrap();
+        assert_eq!(output, data);
+    }
+
+    #[test]
+    fn should_report_error_position() {
+        let input = "cons(:a,\n     :b :c)";
+        let error = run_parser(input, parse, "data").unwrap_err();
+        assert!(matches!(error, crate::error::ParseError::UnbalancedBrackets(_)));
+        let error = error.syntax();
+        assert_eq!(error.offset, 17);
+        assert_eq!(error.line, 2);
+        assert_eq!(error.column, 9);
+        assert_eq!(error.expected, vec!["`,`".into(), "`)`".into()] as Vec<Box<str>>);
+        assert_eq!(&*error.snippet, "     :b :c)\n        ^");
+    }
+
+    #[test]
+    fn should_report_trailing_input() {
+        let input = "[:a] :b";
+        let error = run_parser(input, parse, "data").unwrap_err();
+        assert!(matches!(error, crate::error::ParseError::TrailingInput(_)));
+        let error = error.syntax();
+        assert_eq!(error.offset, 5);
+        assert_eq!(error.expected, vec!["end of input".into()] as Vec<Box<str>>);
+    }
+
+    #[test]
+    fn should_report_missing_data() {
+        fn t(input : &str, offset : usize, expected : &str) {
+            let error = run_parser(input, parse, "data").unwrap_err();
+            let error = error.syntax();
+            assert_eq!(error.offset, offset, "{input}");
+            assert_eq!(error.expected, vec![expected.into()] as Vec<Box<str>>, "{input}");
+        }
+
+        t("", 0, "data");
+        t("  ", 2, "data");
+        t("{ a:  }", 6, "data");
+        t("{ a b }", 4, "`:`");
+        t("{ a: 1, a: 2 }", 8, "a key that isn't already in the record");
+        t("[{ b: { a: 1, c: 2, a: 3 } }]", 20, "a key that isn't already in the record");
+        t("\"\\q\"", 2, "escape code");
+    }
+
+    #[test]
+    fn should_report_error_kind() {
+        use crate::error::ParseError::*;
+
+        assert!(matches!("\"abc".parse::<Data>(), Err(UnterminatedString(_))));
+        assert!(matches!("[\"\\q\"]".parse::<Data>(), Err(BadEscape(_))));
+        assert!(matches!("cons(:a".parse::<Data>(), Err(UnbalancedBrackets(_))));
+        assert!(matches!("{ a: :b ]".parse::<Data>(), Err(UnbalancedBrackets(_))));
+        assert!(matches!(":a :b".parse::<Data>(), Err(TrailingInput(_))));
+        assert!(matches!("{ a :b, c }".parse::<Data>(), Err(Unexpected(_))));
+        assert!(matches!("{ a: 1, a: 1 }".parse::<Data>(), Err(Unexpected(_))));
+        assert!(matches!("".parse::<Data>(), Err(Unexpected(_))));
+    }
+
+    #[test]
+    fn should_display_error() {
+        let error = "[:a, :b".parse::<Data>().unwrap_err();
+        assert_eq!(format!("{}", error), "Unbalanced brackets: Expected `,` or `]` at line 1, column 8\n[:a, :b\n       ^");
+    }
+
     #[test]
     fn should_parse_list() {
         let input = " [ [], [:a, :b], [:c , :d, :e], :f] ";
@@ -168,4 +374,64 @@ mod test {
 
         assert!(matched);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn should_parse_comments() {
+        let input = "// leading comment
+                     cons( :a // after a
+                         , /* before b */ :b
+                         , { x /* between key and colon */ : 1 }
+                         ) /* trailing */";
+        let data = input.parse::<Data>().unwrap();
+        assert_eq!(data, "cons(:a, :b, { x: 1 })".parse::<Data>().unwrap());
+    }
+
+    #[test]
+    fn should_report_unterminated_comment() {
+        let output = "[:a, /* :b ]".parse::<Data>();
+        assert!(matches!(output, Err(crate::error::ParseError::UnterminatedComment(_))));
+    }
+
+    #[test]
+    fn should_allow_single_trailing_comma() {
+        assert_eq!("[:a, :b,]".parse::<Data>().unwrap(), "[:a, :b]".parse::<Data>().unwrap());
+        assert_eq!("cons(:a,)".parse::<Data>().unwrap(), "cons(:a)".parse::<Data>().unwrap());
+        assert_eq!("{ a: 1, }".parse::<Data>().unwrap(), "{ a: 1 }".parse::<Data>().unwrap());
+        assert!("[,]".parse::<Data>().is_err());
+        assert!("[:a,,]".parse::<Data>().is_err());
+        assert!("[:a,, :b]".parse::<Data>().is_err());
+    }
+
+    #[test]
+    fn should_parse_quoted_names() {
+        let input = "`std::vec::Vec`(:`a.b`, `+`(1, 2), { `content-type`: :json })";
+        let data = input.parse::<Data>().unwrap();
+        let expected = Data::Cons { name: "std::vec::Vec".into()
+                                  , params: vec![ Data::SymStr(SymStr::Symbol("a.b".into()))
+                                                , Data::Cons { name: "+".into(), params: vec![1.into(), 2.into()] }
+                                                , Data::Record(vec![("content-type".into(), Data::SymStr(SymStr::Symbol("json".into())))])
+                                                ]
+                                  };
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn should_display_quoted_names_so_they_parse_back() {
+        let input = "`foo-bar`(:`a.b`, :plain, `+`(), {`a key`: :`with \\` tick`, ok: 1})";
+        let data = input.parse::<Data>().unwrap();
+        let output = format!("{}", data);
+        assert_eq!(output, input);
+        assert_eq!(output.parse::<Data>().unwrap(), data);
+    }
+
+    #[test]
+    fn should_parse_nullary_cons() {
+        let input = "[none(), some(nil ( ))]";
+        let data = input.parse::<Data>().unwrap();
+        let expected = Data::List(vec![ Data::Cons { name: "none".into(), params: vec![] }
+                                      , Data::Cons { name: "some".into(), params: vec![Data::Cons { name: "nil".into(), params: vec![] }] }
+                                      ]);
+        assert_eq!(data, expected);
+        assert_eq!(format!("{}", data), "[none(), some(nil())]");
+    }
+}
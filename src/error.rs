@@ -0,0 +1,90 @@
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxError {
+    pub offset : usize,
+    pub line : usize,
+    pub column : usize,
+    pub expected : Vec<Box<str>>,
+    pub snippet : Box<str>,
+}
+
+impl SyntaxError {
+    pub (crate) fn new(input : &str, offset : usize, expected : Vec<&'static str>) -> Self {
+        let before = &input[..offset];
+        let line_start = before.rfind('\n').map(|x| x + 1).unwrap_or(0);
+        let line_end = input[offset..].find('\n').map(|x| x + offset).unwrap_or(input.len());
+        let line = before.matches('\n').count() + 1;
+        let column = before[line_start..].chars().count() + 1;
+
+        // Note:  tabs are kept so that the caret lines up with the text above it
+        let caret = before[line_start..].chars().map(|c| if c == '\t' { '\t' } else { ' ' }).collect::<String>();
+        let snippet = format!("{}\n{}^", &input[line_start..line_end], caret);
+
+        SyntaxError { offset
+                    , line
+                    , column
+                    , expected: expected.into_iter().map(|x| x.into()).collect()
+                    , snippet: snippet.into()
+                    }
+    }
+}
+
+impl std::fmt::Display for SyntaxError {
+    fn fmt(&self, f : &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Expected {} at line {}, column {}\n{}", self.expected.join(" or "), self.line, self.column, self.snippet)
+    }
+}
+
+impl std::error::Error for SyntaxError { }
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    UnterminatedString(SyntaxError),
+    UnterminatedComment(SyntaxError),
+    UnterminatedName(SyntaxError),
+    BadEscape(SyntaxError),
+    BadRegex(SyntaxError),
+    FeatureDisabled(SyntaxError),
+    ExpectedWord(SyntaxError),
+    UnbalancedBrackets(SyntaxError),
+    TrailingInput(SyntaxError),
+    Unexpected(SyntaxError),
+}
+
+impl ParseError {
+    pub fn syntax(&self) -> &SyntaxError {
+        match self {
+            ParseError::UnterminatedString(e) => e,
+            ParseError::UnterminatedComment(e) => e,
+            ParseError::UnterminatedName(e) => e,
+            ParseError::BadEscape(e) => e,
+            ParseError::BadRegex(e) => e,
+            ParseError::FeatureDisabled(e) => e,
+            ParseError::ExpectedWord(e) => e,
+            ParseError::UnbalancedBrackets(e) => e,
+            ParseError::TrailingInput(e) => e,
+            ParseError::Unexpected(e) => e,
+        }
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f : &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ParseError::UnterminatedString(e) => write!(f, "Unterminated string: {}", e),
+            ParseError::UnterminatedComment(e) => write!(f, "Unterminated comment: {}", e),
+            ParseError::UnterminatedName(e) => write!(f, "Unterminated name: {}", e),
+            ParseError::BadEscape(e) => write!(f, "Bad escape: {}", e),
+            ParseError::BadRegex(e) => write!(f, "Bad regex: {}", e),
+            ParseError::FeatureDisabled(e) => write!(f, "Feature disabled: {}", e),
+            ParseError::ExpectedWord(e) => write!(f, "Expected word: {}", e),
+            ParseError::UnbalancedBrackets(e) => write!(f, "Unbalanced brackets: {}", e),
+            ParseError::TrailingInput(e) => write!(f, "Trailing input: {}", e),
+            ParseError::Unexpected(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ParseError { }
+
+
@@ -0,0 +1,361 @@
+
+use std::cmp::Ordering;
+
+use serde_json::{Map, Number, Value};
+
+use crate::data::{SymStr, compare_int_float};
+use crate::error::ParseError;
+use crate::pattern::data::*;
+
+// Note:  JsonAtom is a transparent wrapper so that scalars inside of a Value 
+// can be handed to the matcher as atoms without copying them
+#[derive(Debug, Clone)]
+#[repr(transparent)]
+pub struct JsonAtom(Value);
+
+impl JsonAtom {
+    /// Wraps a scalar for use in a `Pattern<JsonAtom>`.  Arrays and objects are matched by 
+    /// structure instead of as atoms, so an atom that holds one never matches anything.
+    pub fn new(value : Value) -> Self {
+        JsonAtom(value)
+    }
+
+    fn from_ref(value : &Value) -> &JsonAtom {
+        // Safety:  `#[repr(transparent)]` guarantees that JsonAtom has exactly the layout and 
+        // alignment of its only field, so a valid `&Value` is also a valid `&JsonAtom`.  The 
+        // returned reference borrows from `value`, so it can't outlive the Value it points at.
+        unsafe { &*(value as *const Value as *const JsonAtom) }
+    }
+
+    pub fn value(&self) -> &Value {
+        &self.0
+    }
+
+    pub fn into_value(self) -> Value {
+        self.0
+    }
+}
+
+impl From<Value> for JsonAtom {
+    fn from(value : Value) -> Self {
+        JsonAtom::new(value)
+    }
+}
+
+impl PartialEq for JsonAtom {
+    fn eq(&self, other : &Self) -> bool {
+        matches!(self.partial_cmp(other), Some(Ordering::Equal))
+    }
+}
+
+impl PartialOrd for JsonAtom {
+    fn partial_cmp(&self, other : &Self) -> Option<Ordering> {
+        match (&self.0, &other.0) {
+            (Value::Null, Value::Null) => Some(Ordering::Equal),
+            (Value::Bool(a), Value::Bool(b)) => a.partial_cmp(b),
+            (Value::String(a), Value::String(b)) => a.partial_cmp(b),
+            (Value::Number(a), Value::Number(b)) => compare_numbers(a, b),
+            _ => None,
+        }
+    }
+}
+
+// Note:  a Number holds an i64, a u64, or an f64.  Going through f64 would make different integers 
+// past 2^53 equal, so integers are compared as integers and against floats with the exact comparison.
+fn compare_numbers(a : &Number, b : &Number) -> Option<Ordering> {
+    fn int(n : &Number) -> Option<i128> {
+        n.as_i64().map(i128::from).or_else(|| n.as_u64().map(i128::from))
+    }
+
+    match (int(a), int(b)) {
+        (Some(a), Some(b)) => Some(a.cmp(&b)),
+        (Some(a), None) => compare_int_float(a, b.as_f64()?),
+        (None, Some(b)) => compare_int_float(b, a.as_f64()?).map(Ordering::reverse),
+        (None, None) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+    }
+}
+
+impl std::fmt::Display for JsonAtom {
+    fn fmt(&self, f : &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self.0 {
+            Value::Null => write!(f, ":null"),
+            Value::Bool(b) => write!(f, ":{}", b),
+            Value::String(s) => write!(f, "{}", SymStr::String(s.as_str().into())),
+            // Note:  u64 values past the largest i64 are written as integers even though pattern 
+            // syntax can't read them back, since writing them as floats would change their value
+            Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+                (Some(i), _) => write!(f, "{}", SymStr::Int(i)),
+                (None, Some(u)) => write!(f, "{}", u),
+                (None, None) => write!(f, "{}", SymStr::Float(n.as_f64().unwrap_or(f64::NAN))),
+            },
+            other => write!(f, "{}", other),
+        }
+    }
+}
+
+impl Matchable for Value {
+    type Atom = JsonAtom;
+    type Object = Map<String, Value>;
+
+    fn kind(&self) -> MatchKind<Self> {
+        match self {
+            Value::Array(vs) => MatchKind::List(vs),
+            Value::Object(o) => MatchKind::Object(o),
+            scalar => MatchKind::Atom(JsonAtom::from_ref(scalar)),
+        }
+    }
+
+    fn to_pattern(&self) -> Pattern<Self::Atom> {
+        match self {
+            Value::Array(vs) => Pattern::ExactList(vs.iter().map(|x| x.to_pattern()).collect()),
+            Value::Object(o) => Pattern::Object(o.iter().map(|(k, x)| (k.as_str().into(), x.to_pattern())).collect()),
+            scalar => Pattern::Atom(JsonAtom(scalar.clone())),
+        }
+    }
+
+    fn field<'a>(object : &'a Map<String, Value>, key : &str) -> Option<&'a Self> {
+        object.get(key)
+    }
+
+    fn fields<'a>(object : &'a Map<String, Value>) -> Vec<&'a str> {
+        object.keys().map(|k| k.as_str()).collect()
+    }
+
+    fn compare(a : &JsonAtom, b : &JsonAtom) -> Option<Ordering> {
+        a.partial_cmp(b)
+    }
+
+    // Note:  null and the booleans are written as symbols in patterns, so they count as symbols 
+    // and their text is the symbol's name
+    fn atom_text(atom : &JsonAtom) -> Option<&str> {
+        match atom.value() {
+            Value::String(s) => Some(s),
+            Value::Null => Some("null"),
+            Value::Bool(true) => Some("true"),
+            Value::Bool(false) => Some("false"),
+            _ => None,
+        }
+    }
+
+    fn atom_kind(atom : &JsonAtom) -> Option<KindTest> {
+        Some(match atom.value() {
+            Value::String(_) => KindTest::String,
+            Value::Number(_) => KindTest::Number,
+            _ => KindTest::Symbol,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum JsonPatternError {
+    Parse(ParseError),
+    NotJsonScalar(SymStr),
+}
+
+impl std::fmt::Display for JsonPatternError {
+    fn fmt(&self, f : &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            JsonPatternError::Parse(e) => write!(f, "{}", e),
+            JsonPatternError::NotJsonScalar(SymStr::Float(x)) => write!(f, "Float {} is not a JSON number", x),
+            JsonPatternError::NotJsonScalar(s) => write!(f, "Symbol {} is not a JSON scalar", s),
+        }
+    }
+}
+
+impl std::error::Error for JsonPatternError { }
+
+impl From<ParseError> for JsonPatternError {
+    fn from(e : ParseError) -> Self {
+        JsonPatternError::Parse(e)
+    }
+}
+
+fn to_json_atom(atom : SymStr) -> Result<JsonAtom, JsonPatternError> {
+    match atom {
+        SymStr::Symbol(s) if *s == *"null" => Ok(JsonAtom(Value::Null)),
+        SymStr::Symbol(s) if *s == *"true" => Ok(JsonAtom(Value::Bool(true))),
+        SymStr::Symbol(s) if *s == *"false" => Ok(JsonAtom(Value::Bool(false))),
+        SymStr::Symbol(s) => Err(JsonPatternError::NotJsonScalar(SymStr::Symbol(s))),
+        SymStr::String(s) => Ok(JsonAtom(Value::String(s.into()))),
+        SymStr::Int(i) => Ok(JsonAtom(Value::Number(i.into()))),
+        SymStr::Float(x) => match Number::from_f64(x) {
+            Some(n) => Ok(JsonAtom(Value::Number(n))),
+            None => Err(JsonPatternError::NotJsonScalar(SymStr::Float(x))),
+        },
+    }
+}
+
+/// Parses the same syntax as `Pattern<SymStr>`, but atoms are JSON scalars.  
+/// The symbols `:null`, `:true`, and `:false` stand for the JSON literals.
+impl std::str::FromStr for Pattern<JsonAtom> {
+    type Err = JsonPatternError;
+
+    fn from_str(s : &str) -> Result<Self, Self::Err> {
+        let pattern = s.parse::<Pattern<SymStr>>()?;
+        pattern.try_map_atoms(&to_json_atom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+    use crate::pattern::check::*;
+    use crate::pattern::matcher::*;
+
+    fn run<'a>(pattern : &str, data : &'a Value) -> Vec<MatchMap<'a, Value>> {
+        let pattern = check_pattern(pattern.parse::<Pattern<JsonAtom>>().unwrap()).unwrap();
+        pattern_match(&pattern, data).collect()
+    }
+
+    fn node<'a>(map : &MatchMap<'a, Value>, name : &str) -> &'a Value {
+        map.iter().find(|(k, _)| **k == *name).unwrap().1.node().unwrap()
+    }
+
+    #[test]
+    fn should_view_value_as_atom_in_place() {
+        let value = json!("x");
+        let atom = JsonAtom::from_ref(&value);
+        assert!(std::ptr::eq(atom.value(), &value));
+        assert_eq!(std::mem::size_of::<JsonAtom>(), std::mem::size_of::<Value>());
+        assert_eq!(std::mem::align_of::<JsonAtom>(), std::mem::align_of::<Value>());
+    }
+
+    #[test]
+    fn should_build_json_patterns_in_code() {
+        let data = json!({ "id": 7, "tags": ["a", "b"] });
+
+        let pattern = Pattern::Object(vec![ ("id".into(), Pattern::Atom(JsonAtom::new(json!(7))))
+                                          , ("tags".into(), Pattern::ListPath(vec![Pattern::CaptureVar("t".into())]))
+                                          ]);
+        let pattern = check_pattern(pattern).unwrap();
+        assert_eq!(pattern_match(&pattern, &data).count(), 2);
+
+        let pattern = "{ id: 7 }".parse::<Pattern<SymStr>>().unwrap()
+                                 .try_map_atoms(&|a| match a {
+                                     SymStr::Int(i) => Ok(JsonAtom::from(json!(i))),
+                                     other => Err(other),
+                                 }).unwrap();
+        assert_eq!(pattern_match(&check_pattern(pattern).unwrap(), &data).count(), 1);
+        assert_eq!(JsonAtom::new(json!(1)).into_value(), json!(1));
+    }
+
+    #[test]
+    fn should_parse_json_scalars() {
+        let pattern = "[:null, :true, :false, \"x\", 1, 1.5]".parse::<Pattern<JsonAtom>>().unwrap();
+        match pattern {
+            Pattern::ExactList(ps) => {
+                let atoms = ps.into_iter().map(|p| match p { Pattern::Atom(a) => a.0, _ => panic!("expected atom") }).collect::<Vec<_>>();
+                assert_eq!(atoms, vec![json!(null), json!(true), json!(false), json!("x"), json!(1), json!(1.5)]);
+            },
+            _ => panic!("expected exact list"),
+        }
+    }
+
+    #[test]
+    fn should_round_trip_display() {
+        let input = "{ a: [:null, :true, :false, \"x\", 1, 1.5, >= 2] }";
+        let pattern = input.parse::<Pattern<JsonAtom>>().unwrap();
+        let output = format!("{}", pattern);
+        assert_eq!(output, "{a: [:null, :true, :false, \"x\", 1, 1.5, >= 2]}");
+        assert_eq!(output.parse::<Pattern<JsonAtom>>().unwrap(), pattern);
+    }
+
+    #[test]
+    fn should_not_parse_non_json_symbol() {
+        let output = ":blah".parse::<Pattern<JsonAtom>>();
+        assert!(matches!(output, Err(JsonPatternError::NotJsonScalar(SymStr::Symbol(_)))));
+    }
+
+    #[test]
+    fn should_match_object_fields() {
+        let data = json!({ "name": "alice", "age": 30, "admin": false });
+        let results = run("{ name: n, admin: :false }", &data);
+        assert_eq!(results.len(), 1);
+        assert_eq!(node(&results[0], "n"), &json!("alice"));
+    }
+
+    #[test]
+    fn should_match_numbers_by_value() {
+        let data = json!([1, 2.0, 3.5, "2"]);
+        let results = run("[| x.and(2) |]", &data);
+        assert_eq!(results.len(), 1);
+        assert_eq!(node(&results[0], "x"), &json!(2.0));
+    }
+
+    #[test]
+    fn should_match_range_in_array() {
+        let data = json!([{ "size": 100 }, { "size": 8192 }, { "size": null }]);
+        let results = run("[| { size: s.and(> 4096) } |]", &data);
+        assert_eq!(results.len(), 1);
+        assert_eq!(node(&results[0], "s"), &json!(8192));
+    }
+
+    #[test]
+    fn should_match_null() {
+        let data = json!({ "call": [null, 1] });
+        assert_eq!(run("{ call: [:null, _] }", &data).len(), 1);
+        assert_eq!(run("{ call: [_.not(:null), _] }", &data).len(), 0);
+    }
+
+    #[test]
+    fn should_match_string_predicate() {
+        let data = json!(["test_a", "b_test", 1, null]);
+        let results = run("[| x @ #prefix(\"test_\") |]", &data);
+        assert_eq!(results.len(), 1);
+        assert_eq!(node(&results[0], "x"), &json!("test_a"));
+    }
+
+    #[test]
+    fn should_match_kind_tests() {
+        let data = json!(["a", 1, null, true, [], {}]);
+        let results = run("[#string, #number, #symbol, #symbol, #list, #object]", &data);
+        assert_eq!(results.len(), 1);
+        assert_eq!(run("[| #atom.and(x) |]", &data).len(), 4);
+    }
+
+    #[test]
+    fn should_match_predicates_on_null_and_booleans() {
+        let data = json!([true, "true", false, null, 1]);
+        let results = run("[| x.and(#symbol.and(#prefix(\"tr\"))) |]", &data);
+        assert_eq!(results.len(), 1);
+        assert_eq!(node(&results[0], "x"), &json!(true));
+        assert_eq!(run("[| x.and(#contains(\"l\")) |]", &data).len(), 2);
+        assert_eq!(run("[| x.and(#suffix(\"1\")) |]", &data).len(), 0);
+    }
+
+    #[test]
+    fn should_match_template_of_json() {
+        let data = json!([{ "a": [1, 2] }, { "a": [1, 2.0] }]);
+        assert_eq!(run("[x, %x]", &data).len(), 1);
+    }
+
+    #[test]
+    fn should_compare_large_numbers_exactly() {
+        assert_eq!(run("[x, %x]", &json!([u64::MAX, u64::MAX - 1])).len(), 0);
+        assert_eq!(run("[x, %x]", &json!([u64::MAX, u64::MAX])).len(), 1);
+        assert_eq!(run("[x, %x]", &json!([9007199254740993i64, 9007199254740992.0])).len(), 0);
+        assert_eq!(run("[x, %x]", &json!([9007199254740992.0, 9007199254740992u64])).len(), 1);
+        assert_eq!(run("[x, %x]", &json!([-3, -3.0])).len(), 1);
+
+        let big = JsonAtom::new(json!(u64::MAX));
+        assert_eq!(big.partial_cmp(&JsonAtom::new(json!(i64::MAX))), Some(Ordering::Greater));
+        assert_eq!(big.partial_cmp(&JsonAtom::new(json!(1.8446744073709552e19))), Some(Ordering::Less));
+    }
+
+    #[test]
+    fn should_display_large_numbers_as_integers() {
+        assert_eq!(JsonAtom::new(json!(u64::MAX)).to_string(), "18446744073709551615");
+        assert_eq!(JsonAtom::new(json!(-5)).to_string(), "-5");
+        assert_eq!(JsonAtom::new(json!(1.5)).to_string(), "1.5");
+    }
+
+    #[test]
+    fn should_capture_rest_of_array() {
+        let data = json!([1, 2, 3]);
+        let results = run("[_, ..rest]", &data);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0][0].1.slice().unwrap(), &[json!(2), json!(3)]);
+    }
+}
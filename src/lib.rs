@@ -1,12 +1,84 @@
 
 mod parsing;
+pub mod error;
 pub mod data;
 pub mod pattern;
+#[cfg(feature = "json")]
+pub mod json;
 
 
 #[cfg(test)]
 mod tests {
 
+    use crate::data::*;
+    use crate::pattern::matcher::Capture;
+
+    // The capture that a test expects.  A plain string is a node capture.
+    enum Expected {
+        Node(Data),
+        Slice(Vec<Data>),
+        Seq(Vec<Expected>),
+        Name(Box<str>),
+    }
+
+    impl From<&str> for Expected {
+        fn from(input : &str) -> Self {
+            Expected::Node(input.parse().unwrap())
+        }
+    }
+
+    fn items(input : &str) -> Vec<Data> {
+        match input.parse::<Data>().unwrap() {
+            Data::List(ds) => ds,
+            _ => panic!("{input} is not a list"),
+        }
+    }
+
+    fn slice(input : &str) -> Expected {
+        Expected::Slice(items(input))
+    }
+
+    // A sequence of node captures
+    fn seq(input : &str) -> Expected {
+        Expected::Seq(items(input).into_iter().map(Expected::Node).collect())
+    }
+
+    fn seq_of(expected : Vec<Expected>) -> Expected {
+        Expected::Seq(expected)
+    }
+
+    fn name(input : &str) -> Expected {
+        Expected::Name(input.into())
+    }
+
+    // Note:  `==` on data says that `1` and `1.0` are equal, so atoms are also compared by variant here
+    fn exact(a : &Data, b : &Data) -> bool {
+        match (a, b) {
+            (Data::SymStr(a), Data::SymStr(b)) => std::mem::discriminant(a) == std::mem::discriminant(b) && a == b,
+            (Data::Cons { name: a_name, params: a_params }, Data::Cons { name: b_name, params: b_params }) => 
+                a_name == b_name && exact_all(a_params, b_params),
+            (Data::List(a), Data::List(b)) => exact_all(a, b),
+            (Data::Record(a), Data::Record(b)) => 
+                a.len() == b.len() && a.iter().zip(b.iter()).all(|((a_key, a), (b_key, b))| a_key == b_key && exact(a, b)),
+            _ => false,
+        }
+    }
+
+    fn exact_all(a : &[Data], b : &[Data]) -> bool {
+        a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| exact(a, b))
+    }
+
+    fn capture_eq(capture : &Capture<Data>, expected : &Expected) -> bool {
+        match (capture, expected) {
+            (Capture::Node(data), Expected::Node(expected)) => exact(data, expected),
+            (Capture::Slice(data), Expected::Slice(expected)) => exact_all(data, expected),
+            (Capture::Seq(cs), Expected::Seq(expected)) => 
+                cs.len() == expected.len() && cs.iter().zip(expected.iter()).all(|(c, e)| capture_eq(c, e)),
+            (Capture::Name(name), Expected::Name(expected)) => *name == &**expected,
+            _ => false,
+        }
+    }
+
     macro_rules! t {
         ($name:ident $matcher:ident = pattern $pat:expr; data $dat:expr; $({ $($s:expr => $d:expr);* })* ) => {
             #[test]
@@ -32,9 +104,9 @@ mod tests {
                     let mut expected_map_count = 0;
 
                     $(
-                        let data = *_r.get($s).unwrap();
+                        let expected : Expected = $d.into();
                         expected_map_count += 1;
-                        assert_eq!( data, &$d.parse::<Data>().unwrap(), "{} found incorrect result", $s);
+                        assert!( capture_eq(_r.get($s).unwrap(), &expected), "{} found incorrect result", $s);
                     )*
 
                     assert_eq!( r_len, expected_map_count, "more captures found than were tested" );
@@ -53,6 +125,8 @@ mod tests {
                 use crate::pattern::check::*;
                 #[allow(unused_imports)]
                 use crate::pattern::matcher::pattern_match;
+                #[allow(unused_imports)]
+                use super::{capture_eq, Expected, slice, seq, seq_of, name};
 
                 t! { should_match_path_in_path_when_inner_path_sometimes_fails $target =
                         pattern "{| cons(^, ^), inner1(a, ^), inner2( {| cons(^, ^), %a |} ) |}";
@@ -519,14 +593,649 @@ mod tests {
                         { "a" => "cons(:a)" }
                 }
 
+                t! { should_match_int $target =
+                        pattern "alloc(a, 4096)";
+                        data "alloc(:heap, 4096)";
+                        { "a" => ":heap" }
+                }
+
+                t! { should_fail_match_due_to_int $target =
+                        pattern "alloc(a, 4096)";
+                        data "alloc(:heap, 4095)";
+                }
+
+                t! { should_match_int_against_float_by_value $target =
+                        pattern "[a, 5]";
+                        data "[:x, 5.0]";
+                        { "a" => ":x" }
+                }
+
+                t! { should_match_float_against_int_by_value $target =
+                        pattern "[a, 2.5e1]";
+                        data "[:x, 25]";
+                        { "a" => ":x" }
+                }
+
+                t! { should_not_match_number_against_string $target =
+                        pattern "[a, 5]";
+                        data "[:x, \"5\"]";
+                }
+
+                t! { should_match_greater_than $target =
+                        pattern "{| [^, ^, ^], alloc(a, > 4096) |}";
+                        data "[alloc(:one, 4096), alloc(:two, 8192), alloc(:three, 5000.5)]";
+                        { "a" => ":two" }
+                        { "a" => ":three" }
+                }
+
+                t! { should_match_less_than_or_equal $target =
+                        pattern "[| a.and(<= 2) |]";
+                        data "[1, 2.0, 3, 2.5]";
+                        { "a" => "1" }
+                        { "a" => "2.0" }
+                }
+
+                t! { should_match_range $target =
+                        pattern "[| a.and(1..10) |]";
+                        data "[0, 1, 9.5, 10, :one]";
+                        { "a" => "1" }
+                        { "a" => "9.5" }
+                }
+
+                t! { should_match_inclusive_range $target =
+                        pattern "[| a.and(1..=10) |]";
+                        data "[0, 1, 10, 11]";
+                        { "a" => "1" }
+                        { "a" => "10" }
+                }
+
+                t! { should_match_range_with_exclusive_low $target =
+                        pattern "[| a.and(1<..=10) |]";
+                        data "[0, 1, 1.5, 10, 11]";
+                        { "a" => "1.5" }
+                        { "a" => "10" }
+                }
+
+                t! { should_match_range_of_strings $target =
+                        pattern "[| a.and(\"b\"..\"d\") |]";
+                        data "[\"a\", \"b\", \"cat\", \"d\", :c]";
+                        { "a" => "\"b\"" }
+                        { "a" => "\"cat\"" }
+                }
+
+                t! { should_match_any_atom_with_unbounded_range $target =
+                        pattern "[| a.and(#range) |]";
+                        data "[1, [2], \"b\", cons(:c)]";
+                        { "a" => "1" }
+                        { "a" => "\"b\"" }
+                }
+
+                t! { should_not_match_comparison_against_non_number $target =
+                        pattern "cons(>= 3, < 0.5)";
+                        data "cons(\"4\", :zero)";
+                }
+
+                t! { should_match_number_data_in_template $target =
+                        pattern "[a, %a]";
+                        data "[-1.5, -1.5]";
+                        { "a" => "-1.5" }
+                }
+
+                t! { should_match_rest_at_end_of_exact_list $target =
+                        pattern "[a, ..]";
+                        data "[:one, :two, :three]";
+                        { "a" => ":one" }
+                }
+
+                t! { should_match_rest_at_start_of_exact_list $target =
+                        pattern "[.., a]";
+                        data "[:one, :two, :three]";
+                        { "a" => ":three" }
+                }
+
+                t! { should_match_rest_in_middle_of_exact_list $target =
+                        pattern "[a, .., b]";
+                        data "[:one, :two, :three, :four]";
+                        { "a" => ":one"; "b" => ":four" }
+                }
+
+                t! { should_match_empty_rest $target =
+                        pattern "[a, ..rest, b]";
+                        data "[:one, :two]";
+                        { "a" => ":one"; "b" => ":two"; "rest" => slice("[]") }
+                }
+
+                t! { should_match_only_rest $target =
+                        pattern "[..rest]";
+                        data "[:one, :two]";
+                        { "rest" => slice("[:one, :two]") }
+                }
+
+                t! { should_capture_rest_of_exact_list $target =
+                        pattern "[a, ..rest]";
+                        data "[:one, :two, :three]";
+                        { "a" => ":one"; "rest" => slice("[:two, :three]") }
+                }
+
+                t! { should_not_match_rest_when_exact_list_too_short $target =
+                        pattern "[a, .., b, c]";
+                        data "[:one, :two]";
+                }
+
+                t! { should_match_rest_in_cons $target =
+                        pattern "cons(a, .., b)";
+                        data "cons(:one, :two, :three, :four)";
+                        { "a" => ":one"; "b" => ":four" }
+                }
+
+                t! { should_capture_rest_of_cons $target =
+                        pattern "cons(..rest, :last)";
+                        data "cons(:one, :two, :last)";
+                        { "rest" => slice("[:one, :two]") }
+                }
+
+                t! { should_not_match_rest_in_cons_with_wrong_name $target =
+                        pattern "other(..)";
+                        data "cons(:one, :two)";
+                }
+
+                t! { should_match_template_after_rest $target =
+                        pattern "[a, .., %a]";
+                        data "[:one, :two, :one]";
+                        { "a" => ":one" }
+                }
+
+                t! { should_match_not $target =
+                        pattern "call(a, _.not(:null))";
+                        data "call(:f, :x)";
+                        { "a" => ":f" }
+                }
+
+                t! { should_not_match_not $target =
+                        pattern "call(a, _.not(:null))";
+                        data "call(:f, :null)";
+                }
+
+                t! { should_match_not_after_capture $target =
+                        pattern "[| a.not(cons(..)) |]";
+                        data "[:one, cons(:two), [:three]]";
+                        { "a" => ":one" }
+                        { "a" => "[:three]" }
+                }
+
+                t! { should_match_not_with_path $target =
+                        pattern "[| a.not({| [^, ^], :bad |}) |]";
+                        data "[[:x, :bad], [:y, :z], :w]";
+                        { "a" => "[:y, :z]" }
+                        { "a" => ":w" }
+                }
+
+                t! { should_match_not_with_template $target =
+                        pattern "[a, _.not(%a)]";
+                        data "[:one, :two]";
+                        { "a" => ":one" }
+                }
+
+                t! { should_not_match_not_with_template $target =
+                        pattern "[a, _.not(%a)]";
+                        data "[:one, :one]";
+                }
+
+                t! { should_match_not_of_not $target =
+                        pattern "[a, _.not(_.not(:one))]";
+                        data "[:x, :one]";
+                        { "a" => ":x" }
+                }
+
+                t! { should_not_match_fail $target =
+                        pattern "!";
+                        data ":whatever";
+                }
+
+                t! { should_not_match_fail_inside_cons $target =
+                        pattern "cons(a, !)";
+                        data "cons(:a, :b)";
+                }
+
+                t! { should_match_or_with_fail $target =
+                        pattern "cons(a).or(!)";
+                        data "cons(:one)";
+                        { "a" => ":one" }
+                }
+
+                t! { should_match_fail_or_pattern $target =
+                        pattern "!.or(cons(a))";
+                        data "cons(:one)";
+                        { "a" => ":one" }
+                }
+
+                t! { should_match_not_fail $target =
+                        pattern "_.not(!)";
+                        data ":whatever";
+                        { }
+                }
+
+                t! { should_match_record $target =
+                        pattern "{ age: a, name: n }";
+                        data "{ name: :alice, age: 30, kind: :person }";
+                        { "a" => "30"; "n" => ":alice" }
+                }
+
+                t! { should_not_match_record_with_missing_field $target =
+                        pattern "{ name: n, email: e }";
+                        data "{ name: :alice, age: 30 }";
+                }
+
+                t! { should_match_records_in_list_path $target =
+                        pattern "[| alloc({ size: > 4096, owner: o }) |]";
+                        data "[alloc({ size: 100, owner: :a }), alloc({ owner: :b, size: 8192 }), free({ size: 9000, owner: :c })]";
+                        { "o" => ":b" }
+                }
+
+                t! { should_match_path_through_record $target =
+                        pattern "{| { left: ^, right: ^ }, [x, ..] |}";
+                        data "{ left: [:a, :b], right: [:c], other: [:d] }";
+                        { "x" => ":a" }
+                        { "x" => ":c" }
+                }
+
+                t! { should_match_record_data_in_template $target =
+                        pattern "[a, %a]";
+                        data "[{ x: 1, y: :two }, { y: :two, x: 1.0 }]";
+                        { "a" => "{ x: 1, y: :two }" }
+                }
+
                 t! { should_match_string_data_in_template $target =
                         pattern "[a, %a]";
                         data "[\"a\", \"a\"]";
                         { "a" => "\"a\"" }
                 }
+
+                t! { should_match_nullary_cons $target =
+                        pattern "[none(), some(x)]";
+                        data "[none(), some(:a)]";
+                        { "x" => ":a" }
+                }
+
+                t! { should_not_match_nullary_cons_with_params $target =
+                        pattern "none()";
+                        data "none(:a)";
+                }
+
+                t! { should_not_match_nullary_cons_against_symbol $target =
+                        pattern "none()";
+                        data ":none";
+                }
+
+                t! { should_not_match_nullary_cons_with_different_name $target =
+                        pattern "nil()";
+                        data "none()";
+                }
+
+                t! { should_match_as_pattern $target =
+                        pattern "b @ cons(x, y)";
+                        data "cons(:a, :b)";
+                        { "b" => "cons(:a, :b)"; "x" => ":a"; "y" => ":b" }
+                }
+
+                t! { should_not_match_as_pattern $target =
+                        pattern "b @ cons(x, y)";
+                        data "other(:a, :b)";
+                }
+
+                t! { should_match_as_pattern_in_list_path $target =
+                        pattern "[| b @ some(x) |]";
+                        data "[none(), some(:a), some(:b)]";
+                        { "b" => "some(:a)"; "x" => ":a" }
+                        { "b" => "some(:b)"; "x" => ":b" }
+                }
+
+                t! { should_match_as_pattern_with_template $target =
+                        pattern "[b @ [x, ..], %b]";
+                        data "[[:a, :b], [:a, :b]]";
+                        { "b" => "[:a, :b]"; "x" => ":a" }
+                }
+
+                t! { should_match_slice_between_markers $target =
+                        pattern "[:start, ..mid, :end]";
+                        data "[:start, :a, :b, :end]";
+                        { "mid" => slice("[:a, :b]") }
+                }
+
+                t! { should_match_slice_between_markers_in_list_path $target =
+                        pattern "[| :start, ..mid, :end |]";
+                        data "[:x, :start, :a, :b, :end, :y, :start, :end]";
+                        { "mid" => slice("[:a, :b]") }
+                        { "mid" => slice("[:a, :b, :end, :y, :start]") }
+                        { "mid" => slice("[]") }
+                }
+
+                t! { should_match_every_slice_after_marker_in_list_path $target =
+                        pattern "[| :start, ..rest |]";
+                        data "[:start, :a, :b]";
+                        { "rest" => slice("[]") }
+                        { "rest" => slice("[:a]") }
+                        { "rest" => slice("[:a, :b]") }
+                }
+
+                t! { should_match_slice_with_captures_in_list_path $target =
+                        pattern "[| open(x), ..body, close(%x) |]";
+                        data "[open(:a), :b, open(:c), close(:c), close(:a)]";
+                        { "x" => ":a"; "body" => slice("[:b, open(:c), close(:c)]") }
+                        { "x" => ":c"; "body" => slice("[]") }
+                }
+
+                t! { should_not_match_slice_in_short_list_path $target =
+                        pattern "[| :a, ..rest, :b, :c |]";
+                        data "[:a, :b]";
+                }
+
+                t! { should_match_every_slice_in_list_path $target =
+                        pattern "[| ..x |]";
+                        data "[:a, :b]";
+                        { "x" => slice("[]") }
+                        { "x" => slice("[:a]") }
+                        { "x" => slice("[:a, :b]") }
+                        { "x" => slice("[:b]") }
+                }
+
+                t! { should_match_star_repetition $target =
+                        pattern "[:start, x*, :end]";
+                        data "[:start, :a, :b, :end]";
+                        { "x" => seq("[:a, :b]") }
+                }
+
+                t! { should_match_empty_star_repetition $target =
+                        pattern "[:start, x*, :end]";
+                        data "[:start, :end]";
+                        { "x" => seq("[]") }
+                }
+
+                t! { should_match_plus_repetition_with_captures $target =
+                        pattern "[pair(k, v)+]";
+                        data "[pair(:a, 1), pair(:b, 2)]";
+                        { "k" => seq("[:a, :b]"); "v" => seq("[1, 2]") }
+                }
+
+                t! { should_not_match_plus_repetition_when_empty $target =
+                        pattern "[pair(k, v)+]";
+                        data "[]";
+                }
+
+                t! { should_not_match_repetition_when_item_fails $target =
+                        pattern "[pair(k, v)+]";
+                        data "[pair(:a, 1), other(:b, 2)]";
+                }
+
+                t! { should_match_optional_repetition $target =
+                        pattern "cons(a, b?)";
+                        data "cons(:x)";
+                        { "a" => ":x"; "b" => seq("[]") }
+                }
+
+                t! { should_match_bounded_repetition_greedily $target =
+                        pattern "[a{1,2}, b*]";
+                        data "[:x, :y, :z]";
+                        { "a" => seq("[:x, :y]"); "b" => seq("[:z]") }
+                        { "a" => seq("[:x]"); "b" => seq("[:y, :z]") }
+                }
+
+                t! { should_not_match_repetition_outside_of_bounds $target =
+                        pattern "[_{2,3}]";
+                        data "[:x, :y, :z, :w]";
+                }
+
+                t! { should_match_repetition_with_or $target =
+                        pattern "[num(x).or(neg(x))*]";
+                        data "[num(1), neg(2), num(3)]";
+                        { "x" => seq("[1, 2, 3]") }
+                }
+
+                t! { should_match_repetition_with_multiple_item_results $target =
+                        pattern "[[| x |]*]";
+                        data "[[:a, :b], [:c]]";
+                        { "x" => seq("[:a, :c]") }
+                        { "x" => seq("[:b, :c]") }
+                }
+
+                t! { should_match_nested_repetition $target =
+                        pattern "[[x*]*]";
+                        data "[[:a, :b], [], [:c]]";
+                        { "x" => seq_of(vec![seq("[:a, :b]"), seq("[]"), seq("[:c]")]) }
+                }
+
+                t! { should_match_repetition_with_template_from_outside $target =
+                        pattern "[a, [%a*]]";
+                        data "[:z, [:z, :z, :z]]";
+                        { "a" => ":z" }
+                }
+
+                t! { should_match_repetition_in_list_path $target =
+                        pattern "[| :start, digit(d)+, :end |]";
+                        data "[:start, digit(1), digit(2), :end, :start, :end, :start, digit(3), :end]";
+                        { "d" => seq("[1, 2]") }
+                        { "d" => seq("[3]") }
+                }
+
+                t! { should_match_repetition_with_rest $target =
+                        pattern "[x+, ..rest]";
+                        data "[:a, :b]";
+                        { "x" => seq("[:a, :b]"); "rest" => slice("[]") }
+                        { "x" => seq("[:a]"); "rest" => slice("[:b]") }
+                }
+
+                t! { should_match_nullary_cons_in_list_path $target =
+                        pattern "[| x.and(nil().or(none())) |]";
+                        data "[:a, nil(), cons(:b, nil()), none()]";
+                        { "x" => "nil()" }
+                        { "x" => "none()" }
+                }
+
+                t! { should_match_descendant_in_pre_order $target =
+                        pattern "**leaf(x)";
+                        data "node(leaf(1), node(leaf(2), leaf(3)), leaf(4))";
+                        { "x" => "1" }
+                        { "x" => "2" }
+                        { "x" => "3" }
+                        { "x" => "4" }
+                }
+
+                t! { should_match_descendant_at_root $target =
+                        pattern "**x";
+                        data "[:a, [:b]]";
+                        { "x" => "[:a, [:b]]" }
+                        { "x" => ":a" }
+                        { "x" => "[:b]" }
+                        { "x" => ":b" }
+                }
+
+                t! { should_match_descendant_through_objects $target =
+                        pattern "**{ name: x }";
+                        data "{ name: :outer, inner: { name: :inner } }";
+                        { "x" => ":outer" }
+                        { "x" => ":inner" }
+                }
+
+                t! { should_match_descendant_with_template $target =
+                        pattern "[a, **pair(%a, x)]";
+                        data "[:k, [pair(:k, 1), pair(:j, 2), [pair(:k, 3)]]]";
+                        { "a" => ":k"; "x" => "1" }
+                        { "a" => ":k"; "x" => "3" }
+                }
+
+                t! { should_not_match_missing_descendant $target =
+                        pattern "**leaf(x)";
+                        data "node(:a, [:b, :c])";
+                }
+
+                t! { should_match_bag_in_any_order $target =
+                        pattern "#[:b, x]";
+                        data "[:a, :b]";
+                        { "x" => ":a" }
+                }
+
+                t! { should_match_every_bag_assignment $target =
+                        pattern "#[x, y]";
+                        data "[:a, :b]";
+                        { "x" => ":a"; "y" => ":b" }
+                        { "x" => ":b"; "y" => ":a" }
+                }
+
+                t! { should_not_match_bag_with_extra_items $target =
+                        pattern "#[:a, x]";
+                        data "[:a, :b, :c]";
+                }
+
+                t! { should_not_match_bag_with_reused_item $target =
+                        pattern "#[:a, :a]";
+                        data "[:a, :b]";
+                }
+
+                t! { should_match_bag_with_remaining_items $target =
+                        pattern "#[:b, ..rest]";
+                        data "[:a, :b, :c]";
+                        { "rest" => seq("[:a, :c]") }
+                }
+
+                t! { should_match_bag_with_template $target =
+                        pattern "#[pair(x, :key), pair(:value, %x), ..]";
+                        data "[pair(:value, 1), pair(:other, :key), pair(2, :key), pair(:value, 2)]";
+                        { "x" => "2" }
+                }
+
+                t! { should_match_empty_bag $target =
+                        pattern "#[..rest]";
+                        data "[]";
+                        { "rest" => seq("[]") }
+                }
+
+                t! { should_match_any_cons_name $target =
+                        pattern "[| _(a, b) |]";
+                        data "[add(1, 2), neg(3), sub(4, 5)]";
+                        { "a" => "1"; "b" => "2" }
+                        { "a" => "4"; "b" => "5" }
+                }
+
+                t! { should_capture_cons_name $target =
+                        pattern "?n(a, ..)";
+                        data "add(1, 2)";
+                        { "n" => name("add"); "a" => "1" }
+                }
+
+                t! { should_capture_cons_name_in_repetition $target =
+                        pattern "[?n(_)*]";
+                        data "[neg(1), abs(2)]";
+                        { "n" => seq_of(vec![name("neg"), name("abs")]) }
+                }
+
+                t! { should_match_cons_name_alternation $target =
+                        pattern "[| op @ (add|sub)(a, b) |]";
+                        data "[add(1, 2), mul(3, 4), sub(5, 6)]";
+                        { "op" => "add(1, 2)"; "a" => "1"; "b" => "2" }
+                        { "op" => "sub(5, 6)"; "a" => "5"; "b" => "6" }
+                }
+
+                t! { should_match_any_cons_with_one_name $target =
+                        pattern "[| (sub)(a, b) |]";
+                        data "[add(1, 2), sub(3, 4)]";
+                        { "a" => "3"; "b" => "4" }
+                }
+
+                t! { should_not_match_any_cons_with_wrong_arity $target =
+                        pattern "_(a, b)";
+                        data "neg(1)";
+                }
+
+                t! { should_match_quoted_underscore_cons_literally $target =
+                        pattern "[| `_`(a) |]";
+                        data "[`_`(1), other(2)]";
+                        { "a" => "1" }
+                }
+
+                t! { should_match_prefix_of_symbols_and_strings $target =
+                        pattern "[| x @ #prefix(\"test_\") |]";
+                        data "[:test_a, \"test_b\", :other, 1, test_c(), \"a_test_\"]";
+                        { "x" => ":test_a" }
+                        { "x" => "\"test_b\"" }
+                }
+
+                t! { should_match_suffix $target =
+                        pattern "[| #suffix(\".rs\").and(x) |]";
+                        data "[\"main.rs\", \"main.c\", :`lib.rs`]";
+                        { "x" => "\"main.rs\"" }
+                        { "x" => ":`lib.rs`" }
+                }
+
+                t! { should_match_contains $target =
+                        pattern "[| #contains(\"mid\").not(#prefix(\"mid\")) |]";
+                        data "[:mid, :amid, :midst, :a_mid_b, :none]";
+                        { }
+                        { }
+                }
+
+                #[cfg(feature = "regex")]
+                t! { should_match_regex $target =
+                        pattern "[| v @ #regex(\"^v\\\\d+\\\\.\\\\d+$\") |]";
+                        data "[\"v1.2\", \"v1.2.3\", :`v10.0`, \"x1.2\"]";
+                        { "v" => "\"v1.2\"" }
+                        { "v" => ":`v10.0`" }
+                }
+
+                t! { should_match_any_symbol $target =
+                        pattern "[| x.and(#symbol) |]";
+                        data "[:a, \"b\", 1, `_`(), :_, c()]";
+                        { "x" => ":a" }
+                        { "x" => ":_" }
+                }
+
+                t! { should_match_literal_underscore_symbol $target =
+                        pattern "[| x.and(:_) |]";
+                        data "[:a, :_, \"_\"]";
+                        { "x" => ":_" }
+                }
+
+                t! { should_match_atom_kinds $target =
+                        pattern "[#string, #number, #number, #atom]";
+                        data "[\"a\", 1, 1.5, :b]";
+                        { }
+                }
+
+                t! { should_match_value_kinds $target =
+                        pattern "[| x @ (#list.or(#cons)) |]";
+                        data "[[], :a, c(1), { a: 1 }, [2]]";
+                        { "x" => "[]" }
+                        { "x" => "c(1)" }
+                        { "x" => "[2]" }
+                }
+
+                t! { should_match_object_kind $target =
+                        pattern "[| x @ #object |]";
+                        data "[[], { a: 1 }, :b]";
+                        { "x" => "{ a: 1 }" }
+                }
+
+                t! { should_not_match_wrong_kind $target =
+                        pattern "#string";
+                        data ":a";
+                }
             }
         };
     }
 
     all!(pattern_match);
+
+    #[test]
+    fn capture_eq_should_check_capture_variant_and_number_kind() {
+        let list = "[1, 2]".parse::<Data>().unwrap();
+        let one = "1".parse::<Data>().unwrap();
+        let items = match &list { Data::List(ds) => ds, _ => unreachable!() };
+
+        assert!(capture_eq(&Capture::Node(&list), &"[1, 2]".into()));
+        assert!(! capture_eq(&Capture::Node(&list), &slice("[1, 2]")));
+        assert!(! capture_eq(&Capture::Slice(items), &"[1, 2]".into()));
+        assert!(! capture_eq(&Capture::Slice(items), &seq("[1, 2]")));
+        assert!(capture_eq(&Capture::Slice(items), &slice("[1, 2]")));
+        assert!(! capture_eq(&Capture::Node(&one), &"1.0".into()));
+        assert!(! capture_eq(&Capture::Name("a"), &":a".into()));
+    }
 }
@@ -1,11 +1,86 @@
 
+use std::cell::RefCell;
 use std::str::Chars;
 use renounce::*;
 
+use crate::data::SymStr;
+use crate::error::{SyntaxError, ParseError as Failure};
+
+// Note:  renounce's fatal errors don't say what was expected and its `!` builds them itself, so 
+// each fatal point records what it expected and how much input was left when it failed.  Every 
+// run of a parser gets its own frame, so a parse that starts while another one is running (for 
+// example from inside a Matchable impl) can't overwrite the outer parse's expectations.
+type Expected = (usize, Vec<&'static str>, fn(SyntaxError) -> Failure);
+
+thread_local! {
+    static EXPECTED : RefCell<Vec<Option<Expected>>> = RefCell::new(vec![]);
+}
+
+// Pops the frame even if the parser panics
+struct Frame;
+
+impl Frame {
+    fn push() -> Frame {
+        EXPECTED.with(|e| e.borrow_mut().push(None));
+        Frame
+    }
+
+    fn take(&self) -> Option<Expected> {
+        EXPECTED.with(|e| e.borrow_mut().last_mut().and_then(|x| x.take()))
+    }
+}
+
+impl Drop for Frame {
+    fn drop(&mut self) {
+        EXPECTED.with(|e| { e.borrow_mut().pop(); });
+    }
+}
+
+pub (crate) fn record_expected(input : &Chars, kind : fn(SyntaxError) -> Failure, expected : Vec<&'static str>) {
+    // Note:  point past any whitespace so the caret lands where the missing token should start
+    let mut input = input.clone();
+    let _ = parse_whitespace(&mut input);
+    EXPECTED.with(|e| if let Some(frame) = e.borrow_mut().last_mut() {
+        *frame = Some((input.as_str().len(), expected, kind));
+    });
+}
+
+pub (crate) fn run_parser<T>(input : &str, parser : fn(&mut Chars) -> Result<T, ParseError>, what : &'static str) -> Result<T, Failure> {
+    let frame = Frame::push();
+    let mut cs = input.chars();
+    let result = parser(&mut cs);
+    let expected = frame.take();
+    match (result, expected) {
+        (Ok(v), _) => Ok(v),
+        // Note:  the parsers passed in here start with `!`, so they only fail without a fatal error if 
+        // something recorded an expectation and then backed out of it.  That is still the best place to point at.
+        (Err(_), Some((rest, expected, kind))) => Err(kind(SyntaxError::new(input, input.len() - rest, expected))),
+        // Note:  the only fatal point that doesn't record anything is the check for the end of input
+        (Err(ParseError::Fatal(_)), None) => Err(Failure::TrailingInput(SyntaxError::new(input, input.len() - cs.as_str().len(), vec!["end of input"]))),
+        (Err(ParseError::Error), None) => Err(Failure::Unexpected(SyntaxError::new(input, input.len() - cs.as_str().len(), vec![what]))),
+    }
+}
+
+// Wraps a parser so that failing records what was expected.  Only use the wrapper at fatal points.
+macro_rules! expecting {
+    ($kind:ident, $name:ident = $parser:ident : $target_type:ty => $($expected:expr),+) => {
+        fn $name(input : &mut Chars) -> Result<$target_type, ParseError> {
+            let result = $parser(input);
+            if let Err(ParseError::Error) = result {
+                crate::parsing::record_expected(input, crate::error::ParseError::$kind, vec![$($expected),+]);
+            }
+            result
+        }
+    };
+}
+
+// Parses `l_bracket target, target, ... r_bracket`.  A single trailing comma after the last target 
+// is allowed, but a lone comma in an empty list or two commas in a row are not.
 macro_rules! parse_list {
-    ($input:ident => $l_bracket:ident, $target:ident : $target_type:ty, $r_bracket:ident) => {
+    ($input:ident => $l_bracket:ident, $target:ident : $target_type:ty, $r_bracket:ident = $close:expr) => {
         {
             pat!(parse_comma: char => () = ',' => ());
+            expecting!(UnbalancedBrackets, expect_r_bracket = $r_bracket : () => "`,`", $close);
 
             fn parse_target_comma(input : &mut Chars) -> Result<$target_type, ParseError> {
                 parser!(input => {
@@ -25,7 +100,7 @@ macro_rules! parse_list {
                 _clear_1 <= parse_whitespace;
                 last_target <= ? $target;
                 _clear_2 <= parse_whitespace;
-                _right_bracket <= ! $r_bracket;
+                _right_bracket <= ! expect_r_bracket;
                 _clear_3 <= parse_whitespace;
                 select {
                     let mut targets = targets;
@@ -42,7 +117,12 @@ macro_rules! parse_list {
 
 pat!(pub(crate) parse_any<'a>: char => char = x => x);
 
+// Note:  comments count as whitespace, so they're allowed anywhere whitespace is.  Line comments 
+// start with `//` and block comments are `/* ... */` (they don't nest).
 pub (crate) fn parse_whitespace(input : &mut Chars) -> Result<(), ParseError> {
+    pat!(parse_slash: char => () = '/' => ());
+    pat!(parse_star: char => () = '*' => ());
+
     fn space(input : &mut Chars) -> Result<(), ParseError> {
         parser!( input => {
             x <= parse_any;
@@ -51,8 +131,60 @@ pub (crate) fn parse_whitespace(input : &mut Chars) -> Result<(), ParseError> {
         })
     }
 
+    fn parse_line_comment(input : &mut Chars) -> Result<(), ParseError> {
+        fn parse_any_but_newline(input : &mut Chars) -> Result<(), ParseError> {
+            parser!(input => {
+                x <= parse_any;
+                where x != '\n';
+                select ()
+            })
+        }
+
+        parser!(input => {
+            _slash_0 <= parse_slash;
+            _slash_1 <= parse_slash;
+            _text <= * parse_any_but_newline;
+            select ()
+        })
+    }
+
+    fn parse_block_end(input : &mut Chars) -> Result<(), ParseError> {
+        parser!(input => {
+            _star <= parse_star;
+            _slash <= parse_slash;
+            select ()
+        })
+    }
+
+    expecting!(UnterminatedComment, expect_block_end = parse_block_end : () => "`*/`");
+
+    fn parse_block_comment(input : &mut Chars) -> Result<(), ParseError> {
+        fn parse_block_char(input : &mut Chars) -> Result<(), ParseError> {
+            let mut lookahead = input.clone();
+            if parse_block_end(&mut lookahead).is_ok() {
+                return Err(ParseError::Error);
+            }
+            parser!(input => {
+                _x <= parse_any;
+                select ()
+            })
+        }
+
+        parser!(input => {
+            _slash <= parse_slash;
+            _star <= parse_star;
+            _text <= * parse_block_char;
+            _end <= ! expect_block_end;
+            select ()
+        })
+    }
+
+    fn options(input : &mut Chars) -> Result<(), ParseError> {
+        alt!(input => space; parse_line_comment; parse_block_comment)
+    }
+
     parser!( input => {
-        _x <= * space;
+        _x <= * options;
         select ()
     })
 }
@@ -65,6 +197,82 @@ pub (crate) fn parse_digit(input : &mut Chars) -> Result<char, ParseError> {
     })
 }
 
+pub (crate) fn parse_number(input : &mut Chars) -> Result<SymStr, ParseError> {
+    pat!(parse_minus: char => char = '-' => '-');
+    pat!(parse_dot: char => char = '.' => '.');
+
+    fn parse_digits(input : &mut Chars) -> Result<String, ParseError> {
+        parser!(input => {
+            first <= parse_digit;
+            rest <= * parse_digit;
+            select {
+                let mut rest = rest;
+                rest.insert(0, first);
+                rest.into_iter().collect::<String>()
+            }
+        })
+    }
+
+    fn parse_fraction(input : &mut Chars) -> Result<String, ParseError> {
+        parser!(input => {
+            _dot <= parse_dot;
+            digits <= parse_digits;
+            select format!(".{}", digits)
+        })
+    }
+
+    fn parse_exponent_sign(input : &mut Chars) -> Result<char, ParseError> {
+        parser!(input => {
+            sign <= parse_any;
+            where sign == '+' || sign == '-';
+            select sign
+        })
+    }
+
+    fn parse_exponent(input : &mut Chars) -> Result<String, ParseError> {
+        parser!(input => {
+            e <= parse_any;
+            where e == 'e' || e == 'E';
+            sign <= ? parse_exponent_sign;
+            digits <= parse_digits;
+            select match sign {
+                Some(sign) => format!("e{}{}", sign, digits),
+                None => format!("e{}", digits),
+            }
+        })
+    }
+
+    fn parse_number_text(input : &mut Chars) -> Result<String, ParseError> {
+        parser!(input => {
+            minus <= ? parse_minus;
+            whole <= parse_digits;
+            fraction <= ? parse_fraction;
+            exponent <= ? parse_exponent;
+            select format!( "{}{}{}{}"
+                          , minus.map(|_| "-").unwrap_or("")
+                          , whole
+                          , fraction.unwrap_or_default()
+                          , exponent.unwrap_or_default()
+                          )
+        })
+    }
+
+    fn is_float(text : &str) -> bool {
+        text.contains(['.', 'e', 'E'])
+    }
+
+    parser!(input => {
+        text <= parse_number_text;
+        where if is_float(&text) { text.parse::<f64>().map_or(false, f64::is_finite) } else { text.parse::<i64>().is_ok() };
+        select if is_float(&text) {
+            SymStr::Float(text.parse().unwrap())
+        }
+        else {
+            SymStr::Int(text.parse().unwrap())
+        }
+    })
+}
+
 pub (crate) fn parse_word(input : &mut Chars) -> Result<Box<str>, ParseError> {
     pat!(underscore: char => char = '_' => '_');
 
@@ -95,6 +303,47 @@ pub (crate) fn parse_word(input : &mut Chars) -> Result<Box<str>, ParseError> {
     })
 }
 
+// Note:  names that aren't plain words can be written between backticks, with `\`` and `\\` 
+// for a literal backtick or backslash
+pub (crate) fn parse_name(input : &mut Chars) -> Result<Box<str>, ParseError> {
+    alt!(input => parse_word; parse_quoted_name)
+}
+
+pub (crate) fn parse_quoted_name(input : &mut Chars) -> Result<Box<str>, ParseError> {
+    pat!(parse_tick: char => () = '`' => ());
+    pat!(parse_slash: char => char = '\\' => '\\');
+
+    fn parse_name_escape(input : &mut Chars) -> Result<char, ParseError> {
+        parser!(input => {
+            _slash <= parse_slash;
+            code <= parse_any;
+            where code == '`' || code == '\\';
+            select code
+        })
+    }
+
+    fn parse_plain_char(input : &mut Chars) -> Result<char, ParseError> {
+        parser!(input => {
+            x <= parse_any;
+            where x != '`' && x != '\\' && x != '\n';
+            select x
+        })
+    }
+
+    fn parse_name_char(input : &mut Chars) -> Result<char, ParseError> {
+        alt!(input => parse_name_escape; parse_plain_char)
+    }
+
+    expecting!(UnterminatedName, expect_end_tick = parse_tick : () => "`` ` ``");
+
+    parser!(input => {
+        _start_tick <= parse_tick;
+        name_chars <= * parse_name_char;
+        _end_tick <= ! expect_end_tick;
+        select name_chars.into_iter().collect::<String>().into()
+    })
+}
+
 pub (crate) fn parse_string(input : &mut Chars) -> Result<Box<str>, ParseError> {
     pat!(parse_n: char => char = 'n' => '\n');
     pat!(parse_r: char => char = 'r' => '\r');
@@ -103,14 +352,62 @@ pub (crate) fn parse_string(input : &mut Chars) -> Result<Box<str>, ParseError>
     pat!(parse_zero: char => char = '0' => '\0');
     pat!(parse_quote: char => char = '"' => '"');
 
+    pat!(parse_x: char => () = 'x' => ());
+    pat!(parse_u: char => () = 'u' => ());
+    pat!(parse_l_curl: char => () = '{' => ());
+    pat!(parse_r_curl: char => () = '}' => ());
+
+    fn parse_hex_digit(input : &mut Chars) -> Result<u32, ParseError> {
+        parser!(input => {
+            x <= parse_any;
+            where x.is_ascii_hexdigit();
+            select x.to_digit(16).unwrap()
+        })
+    }
+
+    // Note:  like Rust, `\xNN` only covers ASCII so that it can't be confused with a byte
+    fn parse_hex(input : &mut Chars) -> Result<char, ParseError> {
+        parser!(input => {
+            _x <= parse_x;
+            high <= parse_hex_digit;
+            low <= parse_hex_digit;
+            where high * 16 + low <= 0x7F;
+            select char::from_u32(high * 16 + low).unwrap()
+        })
+    }
+
+    fn parse_code_point(input : &mut Chars) -> Result<u32, ParseError> {
+        parser!(input => {
+            first <= parse_hex_digit;
+            rest <= * parse_hex_digit;
+            where rest.len() < 6;
+            select rest.into_iter().fold(first, |acc, x| acc * 16 + x)
+        })
+    }
+
+    fn parse_unicode(input : &mut Chars) -> Result<char, ParseError> {
+        parser!(input => {
+            _u <= parse_u;
+            _l_curl <= parse_l_curl;
+            code <= parse_code_point;
+            _r_curl <= parse_r_curl;
+            where char::from_u32(code).is_some();
+            select char::from_u32(code).unwrap()
+        })
+    }
+
     fn parse_code(input : &mut Chars) -> Result<char, ParseError> {
-        alt!(input => parse_n; parse_r; parse_t; parse_slash; parse_zero; parse_quote)
+        alt!(input => parse_n; parse_r; parse_t; parse_slash; parse_zero; parse_quote; parse_hex; parse_unicode)
     }
 
+    expecting!(BadEscape, expect_code = parse_code : char => "escape code");
+
+    expecting!(UnterminatedString, expect_end_quote = parse_quote : char => "`\"`");
+
     fn parse_escape(input : &mut Chars) -> Result<char, ParseError> {
         parser!(input => {
             _slash <= parse_slash;
-            code <= ! parse_code;
+            code <= ! expect_code;
             select code
         })
     }
@@ -130,11 +427,12 @@ pub (crate) fn parse_string(input : &mut Chars) -> Result<Box<str>, ParseError>
     parser!(input => {
         _start_quote <= parse_quote;
         str_chars <= * parse_str_char;
-        _end_quote <= parse_quote;
+        _end_quote <= ! expect_end_quote;
         select str_chars.into_iter().collect::<String>().into()
     })
 }
 
+pub (crate) use expecting;
 pub (crate) use parse_list;
 
 
@@ -142,6 +440,71 @@ pub (crate) use parse_list;
 mod test {
     use super::*;
 
+    #[test]
+    fn should_keep_expectations_of_outer_parse_during_nested_parse() {
+        pat!(parse_x: char => () = 'x' => ());
+        pat!(parse_y: char => () = 'y' => ());
+        expecting!(ExpectedWord, expect_x = parse_x : () => "x");
+        expecting!(Unexpected, expect_y = parse_y : () => "y");
+
+        fn inner(input : &mut Chars) -> Result<(), ParseError> {
+            parser!(input => {
+                _y <= ! expect_y;
+                select ()
+            })
+        }
+
+        // Note:  fails after recording its expectation, but only once a whole other parse has run
+        fn x_then_nested(input : &mut Chars) -> Result<(), ParseError> {
+            let result = expect_x(input);
+            let nested = run_parser("z", inner, "inner").unwrap_err();
+            assert_eq!(nested.syntax().expected, vec!["y".into()]);
+            result
+        }
+
+        fn outer(input : &mut Chars) -> Result<(), ParseError> {
+            parser!(input => {
+                _x <= ! x_then_nested;
+                select ()
+            })
+        }
+
+        let error = run_parser("a", outer, "outer").unwrap_err();
+        assert!(matches!(error, Failure::ExpectedWord(_)));
+        assert_eq!(error.syntax().expected, vec!["x".into()]);
+    }
+
+    #[test]
+    fn should_report_recorded_position_when_parser_fails_without_fatal_error() {
+        pat!(parse_a: char => () = 'a' => ());
+        pat!(parse_x: char => () = 'x' => ());
+        expecting!(ExpectedWord, expect_x = parse_x : () => "x");
+
+        fn a_then_x(input : &mut Chars) -> Result<(), ParseError> {
+            parser!(input => {
+                _a <= parse_a;
+                _x <= expect_x;
+                select ()
+            })
+        }
+
+        fn a_then_fail(input : &mut Chars) -> Result<(), ParseError> {
+            parser!(input => {
+                _a <= parse_a;
+                _x <= parse_x;
+                select ()
+            })
+        }
+
+        let error = run_parser("ab", a_then_x, "a then x").unwrap_err();
+        assert!(matches!(error, Failure::ExpectedWord(_)));
+        assert_eq!((error.syntax().column, error.syntax().expected.clone()), (2, vec!["x".into()]));
+
+        let error = run_parser("ab", a_then_fail, "a then x").unwrap_err();
+        assert!(matches!(error, Failure::Unexpected(_)));
+        assert_eq!(error.syntax().expected, vec!["a then x".into()]);
+    }
+
     #[test]
     fn should_parse_empty_string() {
         let mut input = "\"\"".chars();
@@ -156,4 +519,131 @@ mod test {
         let output = parse_string(&mut cs).unwrap();
         assert_eq!(*output, *"blah \t \n \r \\ \0 \"  ");
     }
+
+    #[test]
+    fn should_fail_unterminated_string() {
+        let mut input = "\"blah".chars();
+        let output = parse_string(&mut input);
+        assert!(matches!(output, Err(ParseError::Fatal(_))));
+    }
+
+    #[test]
+    fn should_parse_comments_as_whitespace() {
+        fn t(input : &str, rest : &str) {
+            let mut cs = input.chars();
+            parse_whitespace(&mut cs).unwrap();
+            assert_eq!(cs.as_str(), rest, "{input}");
+        }
+
+        t("  // comment\n  x", "x");
+        t("// comment", "");
+        t("/* block \n comment */x", "x");
+        t("/**/ /* a */ // b\n /* c * / */ x", "x");
+        t("/ x", "/ x");
+    }
+
+    #[test]
+    fn should_fail_unterminated_block_comment() {
+        let mut input = "/* blah".chars();
+        let output = parse_whitespace(&mut input);
+        assert!(matches!(output, Err(ParseError::Fatal(_))));
+    }
+
+    #[test]
+    fn should_parse_hex_and_unicode_escapes() {
+        fn t(input : &str, expected : &str) {
+            let mut cs = input.chars();
+            let output = parse_string(&mut cs).unwrap();
+            assert_eq!(*output, *expected, "{input}");
+        }
+
+        t("\"\\x41\\x7f\\x00\"", "A\x7f\0");
+        t("\"\\u{41}\\u{e9}\\u{1F600}\"", "A\u{e9}\u{1F600}");
+        t("\"\\u{10FFFF}\"", "\u{10FFFF}");
+    }
+
+    #[test]
+    fn should_fail_bad_hex_and_unicode_escapes() {
+        fn t(input : &str) {
+            let mut cs = input.chars();
+            let output = parse_string(&mut cs);
+            assert!(matches!(output, Err(ParseError::Fatal(_))), "{input}");
+        }
+
+        t("\"\\x80\"");
+        t("\"\\x4\"");
+        t("\"\\xzz\"");
+        t("\"\\u{}\"");
+        t("\"\\u{110000}\"");
+        t("\"\\u{D800}\"");
+        t("\"\\u{0000041}\"");
+        t("\"\\u41\"");
+    }
+
+    #[test]
+    fn should_parse_name() {
+        fn t(input : &str, expected : &str) {
+            let mut cs = input.chars();
+            let output = parse_name(&mut cs).unwrap();
+            assert_eq!(*output, *expected, "{input}");
+        }
+
+        t("blah_1", "blah_1");
+        t("`foo-bar`", "foo-bar");
+        t("`std::vec::Vec`", "std::vec::Vec");
+        t("`+`", "+");
+        t("``", "");
+        t("`a\\`b\\\\c`", "a`b\\c");
+    }
+
+    #[test]
+    fn should_fail_unterminated_name() {
+        let mut input = "`foo(".chars();
+        let output = parse_name(&mut input);
+        assert!(matches!(output, Err(ParseError::Fatal(_))));
+    }
+
+    #[test]
+    fn should_parse_int() {
+        let mut input = "1234".chars();
+        let output = parse_number(&mut input).unwrap();
+        assert!(matches!(output, SymStr::Int(1234)));
+    }
+
+    #[test]
+    fn should_parse_negative_int() {
+        let mut input = "-17".chars();
+        let output = parse_number(&mut input).unwrap();
+        assert!(matches!(output, SymStr::Int(-17)));
+    }
+
+    #[test]
+    fn should_parse_float() {
+        fn t(input : &str, expected : f64) {
+            let mut cs = input.chars();
+            let output = parse_number(&mut cs).unwrap();
+            assert!(matches!(output, SymStr::Float(x) if x == expected), "{input}");
+        }
+
+        t("5.5", 5.5);
+        t("-0.25", -0.25);
+        t("1e3", 1000.0);
+        t("1.5E-2", 0.015);
+        t("-2e+2", -200.0);
+    }
+
+    #[test]
+    fn should_not_consume_dot_without_fraction_digits() {
+        let mut input = "1..10".chars();
+        let output = parse_number(&mut input).unwrap();
+        assert!(matches!(output, SymStr::Int(1)));
+        assert_eq!(input.collect::<String>(), "..10");
+    }
+
+    #[test]
+    fn should_not_parse_int_that_overflows() {
+        let mut input = "99999999999999999999".chars();
+        let output = parse_number(&mut input);
+        assert!(output.is_err());
+    }
 }
\ No newline at end of file
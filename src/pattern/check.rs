@@ -6,7 +6,7 @@ use super::data::*;
 pub type PatternSig = Vec<Box<str>>;
 
 #[derive(Debug)]
-pub struct TypeChecked<T : Clone>(Pattern<T>, PatternSig);
+pub struct TypeChecked<T : Clone>(Pattern<T>, PatternSig, PatternSig);
 
 impl<T : Clone> TypeChecked<T> {
     pub fn pattern<'a>(&'a self) -> &'a Pattern<T> {
@@ -15,6 +15,28 @@ impl<T : Clone> TypeChecked<T> {
     pub fn signature<'a>(&'a self) -> &'a PatternSig {
         &self.1
     }
+    /// The captures from the signature that are inside of a repetition or that hold the remaining 
+    /// items of a bag.  Each of these binds a `Capture::Seq` with one capture per item.
+    pub fn sequence_signature<'a>(&'a self) -> &'a PatternSig {
+        &self.2
+    }
+}
+
+// Note:  only the pattern is serialized; deserializing goes through check_pattern 
+// so that an invalid pattern can't become a TypeChecked
+#[cfg(feature = "serde")]
+impl<T : Clone + serde::Serialize> serde::Serialize for TypeChecked<T> {
+    fn serialize<S : serde::Serializer>(&self, serializer : S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T : Clone + serde::Deserialize<'de>> serde::Deserialize<'de> for TypeChecked<T> {
+    fn deserialize<D : serde::Deserializer<'de>>(deserializer : D) -> Result<Self, D::Error> {
+        let pattern = Pattern::<T>::deserialize(deserializer)?;
+        check_pattern(pattern).map_err(serde::de::Error::custom)
+    }
 }
 
 #[derive(Debug)]
@@ -24,10 +46,13 @@ pub struct TypeMatches<T : Clone>(TypeChecked<T>);
 pub enum TypeCheckError {
     DuplicateSlot,
     OrPatternHasUnequalSig,
+    NotPatternHasCaptures,
     IncorrectNextUsage,
-    ConsPatternsNeedAtLeastOneParam,
+    IncorrectRestUsage,
+    IncorrectRepeatUsage,
     TypeDoesNotMatch { found: PatternSig, expected: PatternSig },
     TemplateReferencesUnknownCaptureVariable(Box<str>),
+    EmptyConsNameChoice,
 }
 
 impl std::fmt::Display for TypeCheckError {
@@ -36,10 +61,13 @@ impl std::fmt::Display for TypeCheckError {
         match self {
             DuplicateSlot => write!(f, "Pattern TypeCheckError: DuplicateSlot"),
             OrPatternHasUnequalSig => write!(f, "Pattern TypeCheckError: OrPatternHasUnequalSig"),
+            NotPatternHasCaptures => write!(f, "Pattern TypeCheckError: NotPatternHasCaptures"),
             IncorrectNextUsage => write!(f, "Pattern TypeCheckError: IncorrectNextUsage"),
-            ConsPatternsNeedAtLeastOneParam => write!(f, "Pattern TypeCheckError: ConsPatternsNeedAtLeastOneParam"),
+            IncorrectRestUsage => write!(f, "Pattern TypeCheckError: IncorrectRestUsage"),
+            IncorrectRepeatUsage => write!(f, "Pattern TypeCheckError: IncorrectRepeatUsage"),
             TypeDoesNotMatch { found, expected } => write!(f, "Pattern TypeCheckError: Types do not match.  Found {:?}, but expected {:?}", found, expected),
             TemplateReferencesUnknownCaptureVariable(var) => write!(f, "Pattern TypeCheckError:  Template references unknown variable: {}", var),
+            EmptyConsNameChoice => write!(f, "Pattern TypeCheckError: EmptyConsNameChoice"),
         }
     }
 }
@@ -52,8 +80,16 @@ pub fn check_pattern<T : Clone>(pattern : Pattern<T>) -> Result<TypeChecked<T>,
         return Err(TypeCheckError::IncorrectNextUsage);
     }
 
-    if ! pattern.to_lax().map(|p| check_cons_have_params(p)).all(|x| x) {
-        return Err(TypeCheckError::ConsPatternsNeedAtLeastOneParam);
+    if ! check_rest_usage(&pattern) {
+        return Err(TypeCheckError::IncorrectRestUsage);
+    }
+
+    if ! check_repeat_usage(&pattern) {
+        return Err(TypeCheckError::IncorrectRepeatUsage);
+    }
+
+    if ! check_cons_names(&pattern) {
+        return Err(TypeCheckError::EmptyConsNameChoice);
     }
 
     if let Some(error) = check_template_usage(&pattern) {
@@ -61,14 +97,105 @@ pub fn check_pattern<T : Clone>(pattern : Pattern<T>) -> Result<TypeChecked<T>,
     }
 
     let sig = pattern_sig(&pattern)?;
-    Ok(TypeChecked(pattern, sig))
+    let sequence_sig = sequence_sig(&pattern);
+    Ok(TypeChecked(pattern, sig, sequence_sig))
+}
+
+/// Like `check_pattern`, but a capture variable may be used more than once.  Every use after 
+/// the first only matches data equal to what the first use captured, so the name shows up 
+/// once in the signature.  Constructor name captures like `?n(..)` still can't be repeated.
+pub fn check_nonlinear_pattern<T : Clone>(pattern : Pattern<T>) -> Result<TypeChecked<T>, TypeCheckError> {
+    check_pattern(repeated_captures_to_templates(pattern, &mut vec![]))
 }
 
-fn check_cons_have_params<T : Clone>(pattern : &Pattern<T>) -> bool {
+// Note:  the traversal order has to agree with the order the matcher visits patterns in, so that 
+// the first use of a name is always captured before any of its repeats are checked
+fn repeated_captures_to_templates<T : Clone>(pattern : Pattern<T>, seen : &mut Vec<Box<str>>) -> Pattern<T> {
+    fn all<T : Clone>(ps : Vec<Pattern<T>>, seen : &mut Vec<Box<str>>) -> Vec<Pattern<T>> {
+        ps.into_iter().map(|p| repeated_captures_to_templates(p, seen)).collect()
+    }
+
+    use Pattern::*;
     match pattern {
-        Pattern::Cons { params, .. } if params.len() == 0 => false,
+        CaptureVar(var) if seen.contains(&var) => TemplateVar(var),
+        CaptureVar(var) => { seen.push(var.clone()); CaptureVar(var) },
+        Cons { name, params } => Cons { name, params: all(params, seen) },
+        // Note:  there's no template for a constructor name, so a repeated `?n` is left for `check_pattern` to reject
+        AnyCons { name, params } => AnyCons { name, params: all(params, seen) },
+        ExactList(ps) => ExactList(all(ps, seen)),
+        Object(ps) => Object(ps.into_iter().map(|(k, p)| (k, repeated_captures_to_templates(p, seen))).collect()),
+        ListPath(ps) => ListPath(all(ps, seen)),
+        Bag(ps) => Bag(all(ps, seen)),
+        Path(ps) => Path(all(ps, seen)),
+        And(a, b) => {
+            let a = repeated_captures_to_templates(*a, seen);
+            let b = repeated_captures_to_templates(*b, seen);
+            And(Box::new(a), Box::new(b))
+        },
+        Or(a, b) => {
+            let mut b_seen = seen.clone();
+            let a = repeated_captures_to_templates(*a, seen);
+            let b = repeated_captures_to_templates(*b, &mut b_seen);
+            for var in b_seen {
+                if ! seen.contains(&var) {
+                    seen.push(var);
+                }
+            }
+            Or(Box::new(a), Box::new(b))
+        },
+        // Note:  anything captured inside of a not is an error, so it isn't available afterwards
+        Not(a) => Not(Box::new(repeated_captures_to_templates(*a, &mut seen.clone()))),
+        Descendant(p) => Descendant(Box::new(repeated_captures_to_templates(*p, seen))),
+        As(var, p) if seen.contains(&var) => And(Box::new(TemplateVar(var)), Box::new(repeated_captures_to_templates(*p, seen))),
+        As(var, p) => { 
+            seen.push(var.clone()); 
+            As(var, Box::new(repeated_captures_to_templates(*p, seen)))
+        },
+        // Note:  captures inside of a repetition are sequences, so they aren't available afterwards
+        Repeat { pattern, min, max } => Repeat { pattern: Box::new(repeated_captures_to_templates(*pattern, &mut seen.clone())), min, max },
+        // Note:  rest captures are slices and are left alone so that reusing one is still a DuplicateSlot
+        p @ (Atom(_) | Range(_, _) | Predicate(_) | Kind(_) | Fail | Wild | Rest(_) | PathNext | TemplateVar(_)) => p,
+    }
+}
+
+fn check_rest_usage<T : Clone>(pattern : &Pattern<T>) -> bool {
+    fn is_rest<T : Clone>(pattern : &Pattern<T>) -> bool {
+        matches!(pattern, Pattern::Rest(_))
+    }
+
+    if is_rest(pattern) {
+        return false;
+    }
+
+    pattern.to_lax().all(|p| match p {
+        Pattern::Cons { params: ps, .. } | Pattern::AnyCons { params: ps, .. } | Pattern::ExactList(ps) | Pattern::ListPath(ps) | Pattern::Bag(ps) => 
+            ps.iter().filter(|x| is_rest(x)).count() <= 1,
+        _ => ! p.l_next().into_iter().any(is_rest),
+    })
+}
+
+// Note:  a choice of no names can't match anything and can't be written back out
+fn check_cons_names<T : Clone>(pattern : &Pattern<T>) -> bool {
+    pattern.to_lax().all(|p| match p {
+        Pattern::AnyCons { name: ConsName::OneOf(names), .. } => names.len() != 0,
         _ => true,
+    })
+}
+
+fn check_repeat_usage<T : Clone>(pattern : &Pattern<T>) -> bool {
+    fn is_repeat<T : Clone>(pattern : &Pattern<T>) -> bool {
+        matches!(pattern, Pattern::Repeat { .. })
     }
+
+    if is_repeat(pattern) {
+        return false;
+    }
+
+    pattern.to_lax().all(|p| match p {
+        Pattern::Repeat { min, max: Some(max), .. } if min > max => false,
+        Pattern::Cons { .. } | Pattern::AnyCons { .. } | Pattern::ExactList(_) | Pattern::ListPath(_) => true,
+        _ => ! p.l_next().into_iter().any(is_repeat),
+    })
 }
 
 fn check_template_usage<T : Clone>(pattern : &Pattern<T>) -> Option<TypeCheckError> {
@@ -79,21 +206,46 @@ fn check_template_usage<T : Clone>(pattern : &Pattern<T>) -> Option<TypeCheckErr
         }
     }
 
+    // Note:  each repeated item is matched on its own with the captures that exist at that point, so to 
+    // keep that independent of how the list gets matched, repetitions only reference captures from before the list
+    fn items<T : Clone>(ps : &[Pattern<T>], available_captures : &mut Vec<Box<str>>) -> Option<TypeCheckError> {
+        let before = available_captures.clone();
+        ps.iter().map(|p| match p {
+            Pattern::Repeat { pattern, .. } => r(pattern, &mut before.clone()),
+            p => r(p, available_captures),
+        }).find(problem)?
+    }
+
     fn r<T : Clone>(pattern : &Pattern<T>, available_captures : &mut Vec<Box<str>>) -> Option<TypeCheckError> {
         use Pattern::*;
         match pattern {
             Atom(_) => None, 
+            Range(_, _) => None,
+            Predicate(_) => None,
+            Kind(_) => None,
             Fail => None, 
             Wild => None,
             CaptureVar(var) => { available_captures.push(var.clone()); None },
-            Cons { params, .. } => params.iter().map(|p| r(p, available_captures)).find(problem)?,
-            ExactList(ps) => ps.iter().map(|p| r(p, available_captures)).find(problem)?,
-            ListPath(ps) => ps.iter().map(|p| r(p, available_captures)).find(problem)?,
+            Cons { params, .. } => items(params, available_captures),
+            // Note:  a captured constructor name isn't a node, so templates can't reference it
+            AnyCons { params, .. } => items(params, available_captures),
+            ExactList(ps) => items(ps, available_captures),
+            Object(ps) => ps.iter().map(|(_, p)| r(p, available_captures)).find(problem)?,
+            // Note:  rest captures bind a slice and not a single item, so templates can't reference them
+            Rest(_) => None,
+            // Note:  captures inside of a repetition are sequences, so templates outside of it can't reference them
+            Repeat { pattern, .. } => r(pattern, &mut available_captures.clone()),
+            ListPath(ps) => items(ps, available_captures),
+            Bag(ps) => items(ps, available_captures),
             PathNext => None,
             Path(ps) => ps.iter().map(|p| r(p, available_captures)).find(problem)?, 
 
             And(a, b) => r(a, available_captures).or(r(b, available_captures)),
             Or(a, b) => r(a, available_captures).or(r(b, available_captures)),
+            Not(a) => r(a, available_captures),
+            // Note:  the as capture isn't finished until its pattern matches, so the pattern can't reference it
+            As(var, p) => r(p, available_captures).or_else(|| { available_captures.push(var.clone()); None }),
+            Descendant(p) => r(p, available_captures),
             TemplateVar(var) if available_captures.iter().find(|x| *x == var).is_none()
                 => Some(TypeCheckError::TemplateReferencesUnknownCaptureVariable(var.clone())),
             TemplateVar(_) => None, 
@@ -111,12 +263,21 @@ fn check_next_usage<T : Clone>(pattern : &Pattern<T>) -> bool {
         use Pattern::*;
         match pattern {
             Atom(_) => Some(0), 
+            Range(_, _) => Some(0),
+            Predicate(_) => Some(0),
+            Kind(_) => Some(0),
             Fail => Some(0), 
             Wild => Some(0),
             CaptureVar(_) => Some(0),
             Cons { params, .. } => params.iter().map(|p| r(p, in_path)).sum(),
+            AnyCons { params, .. } => params.iter().map(|p| r(p, in_path)).sum(),
             ExactList(ps) => ps.iter().map(|p| r(p, in_path)).sum(),
+            Object(ps) => ps.iter().map(|(_, p)| r(p, in_path)).sum(),
+            Rest(_) => Some(0),
+            // Note:  each repeated item is matched on its own, so it can't produce nexts for an outer path
+            Repeat { pattern, .. } => r(&**pattern, false),
             ListPath(ps) => ps.iter().map(|p| r(p, in_path)).sum(),
+            Bag(ps) => ps.iter().map(|p| r(p, in_path)).sum(),
             PathNext if in_path => Some(1),
             PathNext => None,
             Path(ps) if ps.len() == 0 => Some(0),
@@ -150,6 +311,10 @@ fn check_next_usage<T : Clone>(pattern : &Pattern<T>) -> bool {
                     Some(1)
                 }
             },
+            // Note:  a negated pattern is matched on its own, so it can't produce nexts for an outer path
+            Not(a) => r(&**a, false),
+            As(_, p) => r(&**p, in_path),
+            Descendant(p) => r(&**p, false),
             TemplateVar(_) => Some(0),
         }
     }
@@ -195,17 +360,41 @@ pub fn pattern_sig<T : Clone>(pattern : &Pattern<T>) -> Result<PatternSig, TypeC
 
     match pattern {
         Atom(_) => EMPTY, 
+        Range(_, _) => EMPTY,
+        Predicate(_) => EMPTY,
+        Kind(_) => EMPTY,
         Fail => EMPTY, 
         Wild => EMPTY,
         CaptureVar(v) => Ok(vec![v.clone()]),
         Cons { params, .. } => star!(params),
+        AnyCons { name: ConsName::Capture(v), params } => {
+            let mut sig = star!(params)?;
+
+            if sig.contains(v) {
+                Err(TypeCheckError::DuplicateSlot)
+            }
+            else {
+                sig.push(v.clone());
+                sig.sort();
+                Ok(sig)
+            }
+        },
+        AnyCons { params, .. } => star!(params),
         ExactList(ps) => star!(ps),
+        Object(ps) => star!(ps.iter().map(|(_, p)| p)),
+        Rest(Some(v)) => Ok(vec![v.clone()]),
+        Rest(None) => EMPTY,
+        Repeat { pattern, .. } => pattern_sig(pattern),
         ListPath(ps) => star!(ps),
+        Bag(ps) => star!(ps),
         PathNext => EMPTY, 
         Path(ps) => star!(ps),
         And(a, b) => { 
             star!([a, b])
         },
+        // Note:  fail never binds anything, so it is compatible with any signature
+        Or(a, b) if matches!(**a, Fail) => pattern_sig(b),
+        Or(a, b) if matches!(**b, Fail) => pattern_sig(a),
         Or(a, b) => {
             let a_sig = pattern_sig(a)?;
             let b_sig = pattern_sig(b)?;
@@ -217,10 +406,51 @@ pub fn pattern_sig<T : Clone>(pattern : &Pattern<T>) -> Result<PatternSig, TypeC
                 Ok(a_sig)
             }
         },
+        Not(a) => {
+            let a_sig = pattern_sig(a)?;
+
+            if a_sig.len() != 0 {
+                Err(TypeCheckError::NotPatternHasCaptures)
+            }
+            else {
+                EMPTY
+            }
+        },
+        Descendant(p) => pattern_sig(p),
+        As(v, p) => {
+            let mut sig = pattern_sig(p)?;
+
+            if sig.contains(v) {
+                Err(TypeCheckError::DuplicateSlot)
+            }
+            else {
+                sig.push(v.clone());
+                sig.sort();
+                Ok(sig)
+            }
+        },
         TemplateVar(_) => EMPTY,
     }
 }
 
+/// The names in the pattern's signature that are captured inside of a repetition.
+pub fn sequence_sig<T : Clone>(pattern : &Pattern<T>) -> PatternSig {
+    let mut sig = pattern.to_lax()
+                         .filter_map(|p| match p {
+                             Pattern::Repeat { pattern, .. } => pattern_sig(pattern).ok(),
+                             Pattern::Bag(ps) => Some(ps.iter().filter_map(|p| match p {
+                                 Pattern::Rest(Some(name)) => Some(name.clone()),
+                                 _ => None,
+                             }).collect()),
+                             _ => None,
+                         })
+                         .flatten()
+                         .collect::<PatternSig>();
+    sig.sort();
+    sig.dedup();
+    sig
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -273,6 +503,10 @@ mod test {
         t("[a, b, [cons(%a, %c, c, %c)], :three]");
         t("{| [^, a], %c |}");
         t("[| a, b, [%c, %b] |]");
+        t("[a, ..rest, %rest]");
+        t("{ a: %b, b: b }");
+        t("b @ cons(x, %b)");
+        t("b @ [a, %b]");
     }
 
     #[test]
@@ -293,6 +527,16 @@ mod test {
         t("\"x\"");
         t("\"x\".and( :symbol )");
         t("\"x\".or( :symbol )");
+        t("cons(1..10, >= 3, < 0.5)");
+        t("cons(a, ..)");
+        t("[.., a]");
+        t("{| [^, ..rest], x |}");
+        t("_.not(:null)");
+        t("!");
+        t("[a, !].or([a, b])");
+        t("{ name: n, age: _ }");
+        t("{| { kids: ^ }, { name: n } |}");
+        t("{| [^, ^], _.not({| [^], :null |}) |}");
 
         t("{|  |}");
         t("{| x |}");
@@ -315,5 +559,337 @@ mod test {
         t("{| {| ^, :zero |}, :four |}");
         t("{| cons(^), ^ |}");
         t("cons(^)");
+        t("{| [^, ^].not(^), x |}");
+        t("{| [_.not(^)], x |}");
+    }
+
+    #[test]
+    fn check_rest_usage_should_pass() {
+        fn t(input : &str) {
+            let p : Pattern<SymStr> = input.parse().unwrap();
+            let output = check_rest_usage(&p);
+            assert!(output, "{input}");
+        }
+
+        t("[..]");
+        t("[a, ..]");
+        t("[.., a]");
+        t("[a, ..rest, b]");
+        t("cons(..rest)");
+        t("cons(a, .., b)");
+        t("[[..], cons(..)]");
+        t("{| [^, ..], x |}");
+        t("[| a, .. |]");
+        t("[| :start, ..mid, :end |]");
+        t("#[a, ..]");
+        t("#[..rest, a]");
+    }
+
+    #[test]
+    fn check_rest_usage_should_fail() {
+        fn t(input : &str) {
+            let p : Pattern<SymStr> = input.parse().unwrap();
+            let output = check_rest_usage(&p);
+            assert!(!output, "{input}");
+        }
+
+        t("..");
+        t("..rest");
+        t("[.., ..]");
+        t("cons(..a, b, ..c)");
+        t("[| .., a, .. |]");
+        t("#[.., a, ..rest]");
+        t("{| .., x |}");
+        t("[a.and(..)]");
+        t("[a.or(..)]");
+        t("{ a: .. }");
+    }
+
+    #[test]
+    fn pattern_sig_should_reject_captures_in_not() {
+        fn t(input : &str) {
+            let p : Pattern<SymStr> = input.parse().unwrap();
+            let output = pattern_sig(&p);
+            assert!(matches!(output, Err(TypeCheckError::NotPatternHasCaptures)), "{input}");
+        }
+
+        t("_.not(a)");
+        t("[x, _.not([a, :null])]");
+        t("cons(_.not(cons(..rest)))");
+    }
+
+    #[test]
+    fn pattern_sig_should_allow_fail_in_or() {
+        fn t(input : &str, expected : Vec<&str>) {
+            let p : Pattern<SymStr> = input.parse().unwrap();
+            let output = pattern_sig(&p).unwrap();
+            assert_eq!(output, expected.into_iter().map(|x| x.into()).collect::<PatternSig>(), "{input}");
+        }
+
+        t("cons(a, b).or(!)", vec!["a", "b"]);
+        t("!.or(cons(a, b))", vec!["a", "b"]);
+        t("!.or(!)", vec![]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn type_checked_should_round_trip_through_serde() {
+        let p : Pattern<SymStr> = "cons(a, [| b, 1..10 |], ..rest)".parse().unwrap();
+        let checked = check_pattern(p).unwrap();
+        let json = serde_json::to_string(&checked).unwrap();
+        let output : TypeChecked<SymStr> = serde_json::from_str(&json).unwrap();
+        assert_eq!(output.signature(), checked.signature());
+        assert_eq!(serde_json::to_string(&output).unwrap(), json);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn type_checked_should_not_deserialize_invalid_pattern() {
+        let p : Pattern<SymStr> = "[a, a]".parse().unwrap();
+        let json = serde_json::to_string(&p).unwrap();
+        let output = serde_json::from_str::<TypeChecked<SymStr>>(&json);
+        assert!(output.is_err());
+    }
+
+    #[test]
+    fn pattern_sig_should_include_rest_capture() {
+        let p : Pattern<SymStr> = "cons(a, ..rest, b)".parse().unwrap();
+        let sig = pattern_sig(&p).unwrap();
+        assert_eq!(sig, vec!["a".into(), "b".into(), "rest".into()] as PatternSig);
+    }
+
+    #[test]
+    fn check_pattern_should_allow_nullary_cons() {
+        fn t(input : &str, expected : Vec<&str>) {
+            let p : Pattern<SymStr> = input.parse().unwrap();
+            let output = check_pattern(p).unwrap();
+            assert_eq!(*output.signature(), expected.into_iter().map(|x| x.into()).collect::<PatternSig>(), "{input}");
+        }
+
+        t("none()", vec![]);
+        t("none().or(some(_))", vec![]);
+        t("[none(), some(x)]", vec!["x"]);
+        t("{| cons(^, ^), nil() |}", vec![]);
+    }
+
+    #[test]
+    fn check_nonlinear_pattern_should_list_repeated_capture_once() {
+        fn t(input : &str, expected : Vec<&str>) {
+            let p : Pattern<SymStr> = input.parse().unwrap();
+            let output = check_nonlinear_pattern(p).unwrap();
+            assert_eq!(*output.signature(), expected.into_iter().map(|x| x.into()).collect::<PatternSig>(), "{input}");
+        }
+
+        t("[a, a]", vec!["a"]);
+        t("cons(a, b, [a, b, c])", vec!["a", "b", "c"]);
+        t("{| cons(^, a), [a, ^], a |}", vec!["a"]);
+        t("[x, cons(x).or(other(x))]", vec!["x"]);
+        t("x.or(x).and([x, x])", vec!["x"]);
+        t("[a, _.not(a)]", vec!["a"]);
+        t("{ a: x, b: x }", vec!["x"]);
+    }
+
+    #[test]
+    fn check_nonlinear_pattern_should_rewrite_repeats_to_templates() {
+        let p : Pattern<SymStr> = "[a, cons(a, b), b.or(a)]".parse().unwrap();
+        let output = check_nonlinear_pattern(p).unwrap();
+        assert_eq!(*output.pattern(), "[a, cons(%a, b), %b.or(%a)]".parse::<Pattern<SymStr>>().unwrap());
+    }
+
+    #[test]
+    fn check_nonlinear_pattern_should_fail() {
+        fn t(input : &str) {
+            let p : Pattern<SymStr> = input.parse().unwrap();
+            let output = check_nonlinear_pattern(p);
+            assert!(output.is_err(), "{input}");
+        }
+
+        t("[..a, a]");
+        t("[a, ..a]");
+        t("[a.or(b), a]");
+        t("[_.not(a), a]");
+        t("[?n(a), ?n(b)]");
+    }
+
+    #[test]
+    fn check_pattern_should_reject_empty_cons_name_choice() {
+        let p : Pattern<SymStr> = Pattern::ExactList(vec![Pattern::AnyCons { name: ConsName::OneOf(vec![]), params: vec![] }]);
+        let output = check_pattern(p);
+        assert!(matches!(output, Err(TypeCheckError::EmptyConsNameChoice)));
+
+        let p : Pattern<SymStr> = Pattern::AnyCons { name: ConsName::OneOf(vec!["a".into()]), params: vec![] };
+        assert!(check_pattern(p).is_ok());
+    }
+
+    #[test]
+    fn check_pattern_should_still_reject_repeated_capture() {
+        let p : Pattern<SymStr> = "[a, a]".parse().unwrap();
+        let output = check_pattern(p);
+        assert!(matches!(output, Err(TypeCheckError::DuplicateSlot)));
+    }
+
+    #[test]
+    fn pattern_sig_should_include_as_capture() {
+        fn t(input : &str, expected : Vec<&str>) {
+            let p : Pattern<SymStr> = input.parse().unwrap();
+            let output = check_pattern(p).unwrap();
+            assert_eq!(*output.signature(), expected.into_iter().map(|x| x.into()).collect::<PatternSig>(), "{input}");
+        }
+
+        t("b @ cons(x, y)", vec!["b", "x", "y"]);
+        t("[b @ _, a @ c @ :d]", vec!["a", "b", "c"]);
+        t("[b @ cons(x, _), %b]", vec!["b", "x"]);
+        t("{| b @ cons(^, ^), x |}", vec!["b", "x"]);
+    }
+
+    #[test]
+    fn check_pattern_should_reject_bad_as_pattern() {
+        fn t(input : &str) {
+            let p : Pattern<SymStr> = input.parse().unwrap();
+            let output = check_pattern(p);
+            assert!(output.is_err(), "{input}");
+        }
+
+        t("b @ cons(b)");
+        t("[b, b @ _]");
+        t("_.not(b @ _)");
+        t("[b @ ..]");
+        t("b @ cons(x, %b)");
+    }
+
+    #[test]
+    fn check_nonlinear_pattern_should_check_repeated_as_capture() {
+        let p : Pattern<SymStr> = "[b, b @ cons(x)]".parse().unwrap();
+        let output = check_nonlinear_pattern(p).unwrap();
+        assert_eq!(*output.pattern(), "[b, %b.and(cons(x))]".parse::<Pattern<SymStr>>().unwrap());
+        assert_eq!(*output.signature(), vec!["b".into(), "x".into()] as PatternSig);
+    }
+
+    #[test]
+    fn check_pattern_should_handle_descendant_pattern() {
+        fn t(input : &str, expected : Vec<&str>) {
+            let p : Pattern<SymStr> = input.parse().unwrap();
+            let output = check_pattern(p).unwrap();
+            assert_eq!(*output.signature(), expected.into_iter().map(|x| x.into()).collect::<PatternSig>(), "{input}");
+        }
+
+        t("**cons(x, y)", vec!["x", "y"]);
+        t("[a, **%a]", vec!["a"]);
+        t("b @ **leaf(x)", vec!["b", "x"]);
+    }
+
+    #[test]
+    fn check_pattern_should_reject_bad_descendant_pattern() {
+        fn t(input : &str) {
+            let p : Pattern<SymStr> = input.parse().unwrap();
+            let output = check_pattern(p);
+            assert!(output.is_err(), "{input}");
+        }
+
+        t("**..");
+        t("{| **^, x |}");
+        t("**%a");
+    }
+
+    #[test]
+    fn check_pattern_should_include_cons_name_capture() {
+        fn t(input : &str, expected : Vec<&str>) {
+            let p : Pattern<SymStr> = input.parse().unwrap();
+            let output = check_pattern(p).unwrap();
+            assert_eq!(*output.signature(), expected.into_iter().map(|x| x.into()).collect::<PatternSig>(), "{input}");
+        }
+
+        t("?n(a, b)", vec!["a", "b", "n"]);
+        t("_(a, ..rest)", vec!["a", "rest"]);
+        t("(add|sub)(a, b)", vec!["a", "b"]);
+        t("[?n(a)*]", vec!["a", "n"]);
+    }
+
+    #[test]
+    fn check_pattern_should_reject_bad_cons_name_capture() {
+        fn t(input : &str) {
+            let p : Pattern<SymStr> = input.parse().unwrap();
+            let output = check_pattern(p);
+            assert!(output.is_err(), "{input}");
+        }
+
+        t("?n(n)");
+        t("[n, ?n()]");
+        t("[?n(), %n]");
+        t("_(..a, ..b)");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn check_repeat_usage_should_pass() {
+        fn t(input : &str) {
+            let p : Pattern<SymStr> = input.parse().unwrap();
+            assert!(check_repeat_usage(&p), "{input}");
+        }
+
+        t("[a*]");
+        t("cons(a+, b?)");
+        t("[| :a, x{2,3} |]");
+        t("{| [^, x*], y |}");
+        t("[x{2,2}]");
+    }
+
+    #[test]
+    fn check_repeat_usage_should_fail() {
+        fn t(p : Pattern<SymStr>) {
+            assert!(! check_repeat_usage(&p), "{:?}", p);
+        }
+
+        fn repeat(p : Pattern<SymStr>) -> Pattern<SymStr> {
+            Pattern::Repeat { pattern: Box::new(p), min: 0, max: None }
+        }
+
+        t(repeat(Pattern::Wild));
+        t(Pattern::And(Box::new(repeat(Pattern::Wild)), Box::new(Pattern::Wild)));
+        t(Pattern::Object(vec![("a".into(), repeat(Pattern::Wild))]));
+        t(Pattern::ExactList(vec![repeat(repeat(Pattern::Wild))]));
+        t(Pattern::Bag(vec![repeat(Pattern::Wild)]));
+        t(Pattern::Path(vec![repeat(Pattern::Wild)]));
+        t("[x{5,2}]".parse().unwrap());
+    }
+
+    #[test]
+    fn check_pattern_should_give_repetition_sequence_signature() {
+        fn t(input : &str, expected : Vec<&str>, expected_sequences : Vec<&str>) {
+            let p : Pattern<SymStr> = input.parse().unwrap();
+            let output = check_pattern(p).unwrap();
+            assert_eq!(*output.signature(), expected.into_iter().map(|x| x.into()).collect::<PatternSig>(), "{input}");
+            assert_eq!(*output.sequence_signature(), expected_sequences.into_iter().map(|x| x.into()).collect::<PatternSig>(), "{input}");
+        }
+
+        t("[a, b*]", vec!["a", "b"], vec!["b"]);
+        t("[a, pair(b, c)+, d?]", vec!["a", "b", "c", "d"], vec!["b", "c", "d"]);
+        t("[a, [b*]*]", vec!["a", "b"], vec!["b"]);
+        t("[a]", vec!["a"], vec![]);
+        t("#[a, ..rest]", vec!["a", "rest"], vec!["rest"]);
+        t("#[a, ..]", vec!["a"], vec![]);
+    }
+
+    #[test]
+    fn check_pattern_should_reject_bad_templates_with_repetition() {
+        fn t(input : &str) {
+            let p : Pattern<SymStr> = input.parse().unwrap();
+            assert!(check_pattern(p).is_err(), "{input}");
+        }
+
+        t("[a*, %a]");
+        t("[a, %a*]");
+        t("[a*, a]");
+        t("{| [^*], x |}");
+    }
+
+    #[test]
+    fn check_pattern_should_allow_templates_with_repetition() {
+        fn t(input : &str) {
+            let p : Pattern<SymStr> = input.parse().unwrap();
+            assert!(check_pattern(p).is_ok(), "{input}");
+        }
+
+        t("[a, [%a*]]");
+        t("[pair(a, %a)*]");
+    }
+}
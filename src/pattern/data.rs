@@ -1,4 +1,8 @@
 
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+use std::ops::Bound;
+
 use crate::data::*;
 
 use denest::*;
@@ -16,17 +20,55 @@ pub trait Matchable {
 
     fn kind(&self) -> MatchKind<Self> where Self : Sized;
     fn to_pattern(&self) -> Pattern<Self::Atom>;
+
+    /// The value of a field of an object.  Matchables without objects can leave this out.
+    fn field<'a>(_object : &'a Self::Object, _key : &str) -> Option<&'a Self> where Self : Sized {
+        None
+    }
+
+    /// The names of the fields of an object.  Matchables without objects can leave this out.
+    fn fields(_object : &Self::Object) -> Vec<&str> {
+        vec![]
+    }
+
+    /// Orders two atoms for range patterns.  Atoms that can't be ordered never match a range.
+    fn compare(_a : &Self::Atom, _b : &Self::Atom) -> Option<Ordering> {
+        None
+    }
+
+    /// The text that string predicates look at, or `None` for atoms without any text.
+    fn atom_text(_atom : &Self::Atom) -> Option<&str> {
+        None
+    }
+
+    /// Which of `KindTest::Symbol`, `KindTest::String`, or `KindTest::Number` the atom is, if any.
+    fn atom_kind(_atom : &Self::Atom) -> Option<KindTest> {
+        None
+    }
+
+    /// The values directly inside of this one, in the order that descendant patterns visit them.
+    ///
+    /// This plays the part of `Linearizable::l_next` for the matcher.  `Matchable` can't require 
+    /// `Linearizable` because foreign types like `serde_json::Value` can't implement it here.
+    fn children(&self) -> Vec<&Self> where Self : Sized {
+        match self.kind() {
+            MatchKind::Atom(_) => vec![],
+            MatchKind::Object(o) => Self::fields(o).into_iter().filter_map(|k| Self::field(o, k)).collect(),
+            MatchKind::Cons(_, ds) | MatchKind::List(ds) => ds.iter().collect(),
+        }
+    }
 }
 
 impl Matchable for Data {
     type Atom = SymStr;
-    type Object = ();
+    type Object = Vec<(Box<str>, Data)>;
 
     fn kind(&self) -> MatchKind<Self> {
         match self {
             Data::SymStr(s) => MatchKind::Atom(s),
             Data::Cons { name, params } => MatchKind::Cons(name, params),
             Data::List(ds) => MatchKind::List(ds),
+            Data::Record(fs) => MatchKind::Object(fs),
         }
     }
 
@@ -35,41 +77,448 @@ impl Matchable for Data {
             Data::SymStr(s) => Pattern::Atom(s.clone()), 
             Data::Cons { name, params } => Pattern::Cons { name: name.clone(), params: params.iter().map(|x| x.to_pattern()).collect() },
             Data::List(ds) => Pattern::ExactList(ds.iter().map(|x| x.to_pattern()).collect()),
+            Data::Record(fs) => Pattern::Object(fs.iter().map(|(k, x)| (k.clone(), x.to_pattern())).collect()),
         }
     }
+
+    fn field<'a>(object : &'a Self::Object, key : &str) -> Option<&'a Self> {
+        object.iter().find(|(k, _)| **k == *key).map(|(_, x)| x)
+    }
+
+    fn fields<'a>(object : &'a Self::Object) -> Vec<&'a str> {
+        object.iter().map(|(k, _)| &**k).collect()
+    }
+
+    fn children(&self) -> Vec<&Self> {
+        self.l_next()
+    }
+
+    fn compare(a : &SymStr, b : &SymStr) -> Option<Ordering> {
+        a.partial_cmp(b)
+    }
+
+    fn atom_text(atom : &SymStr) -> Option<&str> {
+        match atom {
+            SymStr::String(s) | SymStr::Symbol(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn atom_kind(atom : &SymStr) -> Option<KindTest> {
+        Some(match atom {
+            SymStr::Symbol(_) => KindTest::Symbol,
+            SymStr::String(_) => KindTest::String,
+            SymStr::Int(_) | SymStr::Float(_) => KindTest::Number,
+        })
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Pattern<TAtom : Clone> {
     Atom(TAtom),
+    Range(Bound<TAtom>, Bound<TAtom>),
     Fail,
     Wild,
     CaptureVar(Box<str>),
     Cons { name: Box<str>, params: Vec<Pattern<TAtom>> },
+    AnyCons { name: ConsName, params: Vec<Pattern<TAtom>> },
     ExactList(Vec<Pattern<TAtom>>),
+    Object(Vec<(Box<str>, Pattern<TAtom>)>),
+    Rest(Option<Box<str>>),
+    Repeat { pattern: Box<Pattern<TAtom>>, min: usize, max: Option<usize> },
     ListPath(Vec<Pattern<TAtom>>),
+    Bag(Vec<Pattern<TAtom>>),
     PathNext,
     Path(Vec<Pattern<TAtom>>),
     And(Box<Pattern<TAtom>>, Box<Pattern<TAtom>>),
     Or(Box<Pattern<TAtom>>, Box<Pattern<TAtom>>),
+    Not(Box<Pattern<TAtom>>),
+    As(Box<str>, Box<Pattern<TAtom>>),
+    Predicate(StrPredicate),
+    Kind(KindTest),
+    Descendant(Box<Pattern<TAtom>>),
     TemplateVar(Box<str>), 
 }
 
+/// How an `AnyCons` pattern matches the name of a constructor.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ConsName {
+    Wild,
+    Capture(Box<str>),
+    OneOf(Vec<Box<str>>),
+}
+
+impl ConsName {
+    pub fn matches(&self, name : &str) -> bool {
+        match self {
+            ConsName::Wild => true,
+            ConsName::Capture(_) => true,
+            ConsName::OneOf(names) => names.iter().any(|n| &**n == name),
+        }
+    }
+}
+
+impl Display for ConsName {
+    fn fmt(&self, f : &mut Formatter) -> std::fmt::Result {
+        match self {
+            ConsName::Wild => write!(f, "_"),
+            ConsName::Capture(v) => write!(f, "?{}", Name(v)),
+            ConsName::OneOf(names) => write!(f, "({})", names.iter().map(|n| Name(n).to_string()).collect::<Vec<_>>().join("|")),
+        }
+    }
+}
+
+/// A test on the text of a string or symbol atom.
+///
+/// The `Regex` variant only exists with the `regex` feature, so matches on this enum outside of 
+/// the crate need a wildcard arm.  That way turning the feature on doesn't break them.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum StrPredicate {
+    Prefix(Box<str>),
+    Suffix(Box<str>),
+    Contains(Box<str>),
+    #[cfg(feature = "regex")]
+    Regex(Regex),
+}
+
+impl StrPredicate {
+    pub fn matches(&self, text : &str) -> bool {
+        match self {
+            StrPredicate::Prefix(p) => text.starts_with(&**p),
+            StrPredicate::Suffix(p) => text.ends_with(&**p),
+            StrPredicate::Contains(p) => text.contains(&**p),
+            #[cfg(feature = "regex")]
+            StrPredicate::Regex(r) => r.0.is_match(text),
+        }
+    }
+}
+
+impl Display for StrPredicate {
+    fn fmt(&self, f : &mut Formatter) -> std::fmt::Result {
+        fn string(s : &str) -> SymStr {
+            SymStr::String(s.into())
+        }
+
+        match self {
+            StrPredicate::Prefix(p) => write!(f, "#prefix({})", string(p)),
+            StrPredicate::Suffix(p) => write!(f, "#suffix({})", string(p)),
+            StrPredicate::Contains(p) => write!(f, "#contains({})", string(p)),
+            #[cfg(feature = "regex")]
+            StrPredicate::Regex(r) => write!(f, "#regex({})", string(r.0.as_str())),
+        }
+    }
+}
+
+/// A test on what kind of value is being matched.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum KindTest {
+    Symbol,
+    String,
+    Number,
+    Atom,
+    List,
+    Cons,
+    Object,
+}
+
+impl KindTest {
+    pub fn matches<D : Matchable>(&self, kind : &MatchKind<D>) -> bool {
+        match (self, kind) {
+            (KindTest::Atom, MatchKind::Atom(_)) => true,
+            (KindTest::List, MatchKind::List(_)) => true,
+            (KindTest::Cons, MatchKind::Cons(_, _)) => true,
+            (KindTest::Object, MatchKind::Object(_)) => true,
+            (k, MatchKind::Atom(a)) => D::atom_kind(a) == Some(*k),
+            _ => false,
+        }
+    }
+}
+
+impl Display for KindTest {
+    fn fmt(&self, f : &mut Formatter) -> std::fmt::Result {
+        match self {
+            KindTest::Symbol => write!(f, "#symbol"),
+            KindTest::String => write!(f, "#string"),
+            KindTest::Number => write!(f, "#number"),
+            KindTest::Atom => write!(f, "#atom"),
+            KindTest::List => write!(f, "#list"),
+            KindTest::Cons => write!(f, "#cons"),
+            KindTest::Object => write!(f, "#object"),
+        }
+    }
+}
+
+/// A compiled regular expression that compares and serializes as its source text.
+#[cfg(feature = "regex")]
+#[derive(Debug, Clone)]
+pub struct Regex(regex::Regex);
+
+#[cfg(feature = "regex")]
+impl Regex {
+    pub fn new(source : &str) -> Result<Self, regex::Error> {
+        Ok(Regex(regex::Regex::new(source)?))
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+#[cfg(feature = "regex")]
+impl PartialEq for Regex {
+    fn eq(&self, other : &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+#[cfg(all(feature = "regex", feature = "serde"))]
+impl serde::Serialize for Regex {
+    fn serialize<S : serde::Serializer>(&self, serializer : S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(all(feature = "regex", feature = "serde"))]
+impl<'de> serde::Deserialize<'de> for Regex {
+    fn deserialize<D : serde::Deserializer<'de>>(deserializer : D) -> Result<Self, D::Error> {
+        let source = String::deserialize(deserializer)?;
+        Regex::new(&source).map_err(serde::de::Error::custom)
+    }
+}
+
+// Note:  a plain `_` is the wildcard, so a variable or constructor that is really named `_` needs quoting
+struct Var<'a>(&'a str);
+
+impl<'a> Display for Var<'a> {
+    fn fmt(&self, f : &mut Formatter) -> std::fmt::Result {
+        if self.0 == "_" {
+            write!(f, "`_`")
+        }
+        else {
+            write!(f, "{}", Name(self.0))
+        }
+    }
+}
+
+/// `{}` writes the pattern on one line and `{:#}` writes it indented over multiple lines.  
+/// Both forms use the syntax that `pattern::parse` accepts.
+impl<TAtom : Clone + Display> Display for Pattern<TAtom> {
+    fn fmt(&self, f : &mut Formatter) -> std::fmt::Result {
+        let indent = if f.alternate() { Some(0) } else { None };
+        write_pattern(self, indent, f)
+    }
+}
+
+fn write_pattern<T : Clone + Display>(pattern : &Pattern<T>, indent : Option<usize>, f : &mut Formatter) -> std::fmt::Result {
+    use Pattern::*;
+
+    fn pad(indent : usize) -> String {
+        "    ".repeat(indent)
+    }
+
+    // Note:  simple patterns are written the same way in both modes
+    fn is_simple<T : Clone>(pattern : &Pattern<T>) -> bool {
+        match pattern {
+            Cons { params: ps, .. } | AnyCons { params: ps, .. } | ExactList(ps) | ListPath(ps) | Bag(ps) | Path(ps) => ps.len() == 0,
+            Object(ps) => ps.len() == 0,
+            And(a, b) | Or(a, b) => is_simple(a) && is_simple(b),
+            Not(a) | As(_, a) | Descendant(a) | Repeat { pattern: a, .. } => is_simple(a),
+            _ => true,
+        }
+    }
+
+    fn seq<T : Clone + Display>(open : &str, items : Vec<(String, &Pattern<T>)>, close : &str, indent : Option<usize>, f : &mut Formatter) -> std::fmt::Result {
+        match indent {
+            Some(indent) if ! items.iter().all(|(_, p)| is_simple(p)) => {
+                writeln!(f, "{}", open)?;
+                let last = items.len() - 1;
+                for (i, (prefix, p)) in items.into_iter().enumerate() {
+                    write!(f, "{}{}", pad(indent + 1), prefix)?;
+                    write_pattern(p, Some(indent + 1), f)?;
+                    if i != last {
+                        write!(f, ",")?;
+                    }
+                    writeln!(f)?;
+                }
+                write!(f, "{}{}", pad(indent), close)
+            },
+            _ => {
+                write!(f, "{}", open)?;
+                for (i, (prefix, p)) in items.into_iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", prefix)?;
+                    write_pattern(p, None, f)?;
+                }
+                write!(f, "{}", close)
+            },
+        }
+    }
+
+    fn end<T : Clone + Display>(a : &Pattern<T>, name : &str, b : &Pattern<T>, indent : Option<usize>, f : &mut Formatter) -> std::fmt::Result {
+        match indent {
+            Some(indent) if ! (is_simple(a) && is_simple(b)) => {
+                write_pattern(a, Some(indent), f)?;
+                writeln!(f)?;
+                write!(f, "{}.{}(", pad(indent), name)?;
+                write_pattern(b, Some(indent), f)?;
+                write!(f, ")")
+            },
+            _ => {
+                write_pattern(a, None, f)?;
+                write!(f, ".{}(", name)?;
+                write_pattern(b, None, f)?;
+                write!(f, ")")
+            },
+        }
+    }
+
+    fn unnamed<T : Clone>(ps : &[Pattern<T>]) -> Vec<(String, &Pattern<T>)> {
+        ps.iter().map(|p| ("".to_string(), p)).collect()
+    }
+
+    match pattern {
+        Atom(a) => write!(f, "{}", a),
+        Range(Bound::Included(low), Bound::Excluded(high)) => write!(f, "{}..{}", low, high),
+        Range(Bound::Included(low), Bound::Included(high)) => write!(f, "{}..={}", low, high),
+        Range(Bound::Excluded(low), Bound::Excluded(high)) => write!(f, "{}<..{}", low, high),
+        Range(Bound::Excluded(low), Bound::Included(high)) => write!(f, "{}<..={}", low, high),
+        Range(Bound::Unbounded, Bound::Excluded(high)) => write!(f, "< {}", high),
+        Range(Bound::Unbounded, Bound::Included(high)) => write!(f, "<= {}", high),
+        Range(Bound::Excluded(low), Bound::Unbounded) => write!(f, "> {}", low),
+        Range(Bound::Included(low), Bound::Unbounded) => write!(f, ">= {}", low),
+        Range(Bound::Unbounded, Bound::Unbounded) => write!(f, "#range"),
+        Fail => write!(f, "!"),
+        Wild => write!(f, "_"),
+        CaptureVar(v) => write!(f, "{}", Var(v)),
+        Cons { name, params } => seq(&format!("{}(", Var(name)), unnamed(params), ")", indent, f),
+        AnyCons { name, params } => seq(&format!("{}(", name), unnamed(params), ")", indent, f),
+        ExactList(ps) => seq("[", unnamed(ps), "]", indent, f),
+        Object(ps) => seq("{", ps.iter().map(|(k, p)| (format!("{}: ", Name(k)), p)).collect(), "}", indent, f),
+        Rest(Some(v)) => write!(f, "..{}", Name(v)),
+        Rest(None) => write!(f, ".."),
+        Repeat { pattern, min, max } => {
+            write_pattern(pattern, indent, f)?;
+            match (min, max) {
+                (0, None) => write!(f, "*"),
+                (1, None) => write!(f, "+"),
+                (0, Some(1)) => write!(f, "?"),
+                (min, None) => write!(f, "{{{},}}", min),
+                (min, Some(max)) if min == max => write!(f, "{{{}}}", min),
+                (min, Some(max)) => write!(f, "{{{},{}}}", min, max),
+            }
+        },
+        ListPath(ps) => seq("[|", unnamed(ps), "|]", indent, f),
+        Bag(ps) => seq("#[", unnamed(ps), "]", indent, f),
+        PathNext => write!(f, "^"),
+        Path(ps) => seq("{|", unnamed(ps), "|}", indent, f),
+        And(a, b) => match &**b {
+            // Note:  `_.not(p)` parses as a lone not pattern, so only other patterns can use the shorter form
+            Not(b) if ! matches!(**a, Wild) => end(a, "not", b, indent, f),
+            _ => end(a, "and", b, indent, f),
+        },
+        Or(a, b) => end(a, "or", b, indent, f),
+        Not(a) => end(&Wild, "not", a, indent, f),
+        // Note:  `@` binds tighter than `.and`, `.or`, and `.not`, so those need to be grouped
+        As(v, p) if matches!(**p, And(_, _) | Or(_, _) | Not(_)) => {
+            write!(f, "{} @ (", Var(v))?;
+            write_pattern(p, indent, f)?;
+            write!(f, ")")
+        },
+        As(v, p) => {
+            write!(f, "{} @ ", Var(v))?;
+            write_pattern(p, indent, f)
+        },
+        Predicate(p) => write!(f, "{}", p),
+        Kind(k) => write!(f, "{}", k),
+        Descendant(p) if matches!(**p, And(_, _) | Or(_, _) | Not(_)) => {
+            write!(f, "**(")?;
+            write_pattern(p, indent, f)?;
+            write!(f, ")")
+        },
+        Descendant(p) => {
+            write!(f, "**")?;
+            write_pattern(p, indent, f)
+        },
+        TemplateVar(v) => write!(f, "%{}", Name(v)),
+    }
+}
+
+impl<TAtom : Clone> Pattern<TAtom> {
+    pub fn try_map_atoms<TOther : Clone, E, F : Fn(TAtom) -> Result<TOther, E>>(self, f : &F) -> Result<Pattern<TOther>, E> {
+        fn bound<TAtom, TOther, E, F : Fn(TAtom) -> Result<TOther, E>>(b : Bound<TAtom>, f : &F) -> Result<Bound<TOther>, E> {
+            match b {
+                Bound::Included(x) => Ok(Bound::Included(f(x)?)),
+                Bound::Excluded(x) => Ok(Bound::Excluded(f(x)?)),
+                Bound::Unbounded => Ok(Bound::Unbounded),
+            }
+        }
+
+        fn all<TAtom : Clone, TOther : Clone, E, F : Fn(TAtom) -> Result<TOther, E>>(ps : Vec<Pattern<TAtom>>, f : &F) -> Result<Vec<Pattern<TOther>>, E> {
+            ps.into_iter().map(|p| p.try_map_atoms(f)).collect()
+        }
+
+        use Pattern::*;
+        Ok(match self {
+            Atom(a) => Atom(f(a)?),
+            Range(low, high) => Range(bound(low, f)?, bound(high, f)?),
+            Fail => Fail,
+            Wild => Wild,
+            CaptureVar(v) => CaptureVar(v),
+            Cons { name, params } => Cons { name, params: all(params, f)? },
+            AnyCons { name, params } => AnyCons { name, params: all(params, f)? },
+            ExactList(ps) => ExactList(all(ps, f)?),
+            Object(ps) => Object(ps.into_iter().map(|(k, p)| Ok((k, p.try_map_atoms(f)?))).collect::<Result<_, E>>()?),
+            Rest(v) => Rest(v),
+            Repeat { pattern, min, max } => Repeat { pattern: Box::new(pattern.try_map_atoms(f)?), min, max },
+            ListPath(ps) => ListPath(all(ps, f)?),
+            Bag(ps) => Bag(all(ps, f)?),
+            PathNext => PathNext,
+            Path(ps) => Path(all(ps, f)?),
+            And(a, b) => And(Box::new(a.try_map_atoms(f)?), Box::new(b.try_map_atoms(f)?)),
+            Or(a, b) => Or(Box::new(a.try_map_atoms(f)?), Box::new(b.try_map_atoms(f)?)),
+            Not(a) => Not(Box::new(a.try_map_atoms(f)?)),
+            As(v, p) => As(v, Box::new(p.try_map_atoms(f)?)),
+            Predicate(p) => Predicate(p),
+            Kind(k) => Kind(k),
+            Descendant(p) => Descendant(Box::new(p.try_map_atoms(f)?)),
+            TemplateVar(v) => TemplateVar(v),
+        })
+    }
+}
+
 impl<'a, T : Clone> Linearizable<'a> for Pattern<T> {
     fn l_next(&'a self) -> Vec<&'a Self> {
         use Pattern::*;
         match self {
             Atom(_) => vec![], 
+            Range(_, _) => vec![],
             Fail => vec![],
             Wild => vec![],
             CaptureVar(_) => vec![],
             Cons { params, name: _ } => params.iter().collect(),
+            AnyCons { params, name: _ } => params.iter().collect(),
             ExactList(ps) => ps.iter().collect(),
+            Object(ps) => ps.iter().map(|(_, p)| p).collect(),
+            Rest(_) => vec![],
+            Repeat { pattern, .. } => vec![&**pattern],
             ListPath(ps) => ps.iter().collect(),
+            Bag(ps) => ps.iter().collect(),
             PathNext => vec![],
             Path(ps) => ps.iter().collect(),
             And(a, b) => vec![&**a, &**b],
             Or(a, b) => vec![&**a, &**b],
+            Not(a) => vec![&**a],
+            As(_, p) => vec![&**p],
+            Predicate(_) => vec![],
+            Kind(_) => vec![],
+            Descendant(p) => vec![&**p],
             TemplateVar(_) => vec![],
         }
     }
@@ -1,11 +1,60 @@
 
+use std::cmp::Ordering;
+use std::ops::Bound;
+
 use super::data::*;
 use super::check::*;
 
-// TODO : Consider .. pattern so that cons and exact list can ignore fields
+#[derive(Debug, PartialEq)]
+pub enum Capture<'a, T> {
+    Node(&'a T),
+    Slice(&'a [T]),
+    Seq(Vec<Capture<'a, T>>),
+    Name(&'a str),
+}
+
+impl<'a, T> Capture<'a, T> {
+    pub fn node(&self) -> Option<&'a T> {
+        match self {
+            Capture::Node(x) => Some(x),
+            _ => None,
+        }
+    }
 
+    pub fn slice(&self) -> Option<&'a [T]> {
+        match self {
+            Capture::Slice(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    pub fn seq(&self) -> Option<&[Capture<'a, T>]> {
+        match self {
+            Capture::Seq(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> Option<&'a str> {
+        match self {
+            Capture::Name(x) => Some(x),
+            _ => None,
+        }
+    }
+}
+
+impl<'a, T> Clone for Capture<'a, T> {
+    fn clone(&self) -> Self {
+        match self {
+            Capture::Node(x) => Capture::Node(x),
+            Capture::Slice(x) => Capture::Slice(x),
+            Capture::Seq(x) => Capture::Seq(x.clone()),
+            Capture::Name(x) => Capture::Name(x),
+        }
+    }
+}
 
-pub type MatchMap<'a, T> = Vec<(Box<str>, &'a T)>; 
+pub type MatchMap<'a, T> = Vec<(Box<str>, Capture<'a, T>)>; 
 
 pub fn pattern_match<'a, TAtom : Clone + PartialEq, D : Matchable<Atom=TAtom>>(
     pattern : &TypeChecked<TAtom>, data : &'a D) -> Matches<'a, TAtom, D> {
@@ -17,9 +66,159 @@ pub fn pattern_match<'a, TAtom : Clone + PartialEq, D : Matchable<Atom=TAtom>>(
     Matches { matches: vec![], current_work, future_work: vec![] }
 }
 
+// Note:  a sequence with repetitions is matched one step at a time.  Each step queues whatever is 
+// left of the sequence as another task, so that the other choices can wait in the future work.
+#[derive(Debug)]
+enum Task<'a, TAtom : Clone + PartialEq, D : Matchable<Atom=TAtom>> {
+    Match(Pattern<TAtom>, &'a D),
+    Sequence { ps : Vec<Pattern<TAtom>>, ds : &'a [D], list : &'a D },
+    Repetition(Repetition<'a, TAtom, D>),
+    Bag(Bag<'a, TAtom, D>),
+    Windows(Windows<'a, TAtom, D>),
+}
+
+impl<'a, TAtom : Clone + PartialEq, D : Matchable<Atom=TAtom>> Clone for Task<'a, TAtom, D> {
+    fn clone(&self) -> Self {
+        match self {
+            Task::Match(p, d) => Task::Match(p.clone(), d),
+            Task::Sequence { ps, ds, list } => Task::Sequence { ps: ps.clone(), ds, list },
+            Task::Repetition(r) => Task::Repetition(r.clone()),
+            Task::Bag(b) => Task::Bag(b.clone()),
+            Task::Windows(w) => Task::Windows(w.clone()),
+        }
+    }
+}
+
+// A repetition that has matched `items` so far, followed by the patterns `ps` for the rest of the list
+#[derive(Debug)]
+struct Repetition<'a, TAtom : Clone + PartialEq, D : Matchable<Atom=TAtom>> {
+    pattern : Pattern<TAtom>,
+    min : usize,
+    max : Option<usize>,
+    items : Vec<MatchMap<'a, D>>,
+    ps : Vec<Pattern<TAtom>>,
+    ds : &'a [D],
+    list : &'a D,
+}
+
+impl<'a, TAtom : Clone + PartialEq, D : Matchable<Atom=TAtom>> Clone for Repetition<'a, TAtom, D> {
+    fn clone(&self) -> Self {
+        Repetition { pattern: self.pattern.clone()
+                   , min: self.min
+                   , max: self.max
+                   , items: self.items.clone()
+                   , ps: self.ps.clone()
+                   , ds: self.ds
+                   , list: self.list
+                   }
+    }
+}
+
+impl<'a, TAtom : Clone + PartialEq, D : Matchable<Atom=TAtom>> Repetition<'a, TAtom, D> {
+    // Whether the rest of the list can still line up after one more item
+    fn can_continue(&self) -> bool {
+        let count = self.items.len() + 1;
+        if self.ds.len() == 0 || self.max.map_or(false, |max| max < count) {
+            return false;
+        }
+        let mut ps = vec![Pattern::Repeat { pattern: Box::new(Pattern::Wild)
+                                          , min: self.min.saturating_sub(count)
+                                          , max: self.max.map(|max| max - count)
+                                          }];
+        ps.extend(self.ps.iter().cloned());
+        sequence_fits(&ps, self.ds.len() - 1)
+    }
+
+    fn can_stop(&self) -> bool {
+        self.min <= self.items.len() && sequence_fits(&self.ps, self.ds.len())
+    }
+
+    // The captures of the items gathered into one sequence per name
+    fn captures(&self) -> MatchMap<'a, D> {
+        let names = pattern_sig(&self.pattern).unwrap_or_default();
+        names.into_iter().map(|name| {
+            let items = self.items.iter().map(|m| m.iter().find(|(k, _)| *k == name).unwrap().1.clone()).collect();
+            (name, Capture::Seq(items))
+        }).collect()
+    }
+}
+
+// A bag that still has to place `ps`.  The next pattern tries the unused items starting from `start`.
+#[derive(Debug)]
+struct Bag<'a, TAtom : Clone + PartialEq, D : Matchable<Atom=TAtom>> {
+    ps : Vec<Pattern<TAtom>>,
+    rest : Option<Option<Box<str>>>,
+    ds : &'a [D],
+    used : Vec<bool>,
+    start : usize,
+    list : &'a D,
+}
+
+impl<'a, TAtom : Clone + PartialEq, D : Matchable<Atom=TAtom>> Clone for Bag<'a, TAtom, D> {
+    fn clone(&self) -> Self {
+        Bag { ps: self.ps.clone()
+            , rest: self.rest.clone()
+            , ds: self.ds
+            , used: self.used.clone()
+            , start: self.start
+            , list: self.list
+            }
+    }
+}
+
+impl<'a, TAtom : Clone + PartialEq, D : Matchable<Atom=TAtom>> Bag<'a, TAtom, D> {
+    fn new(mut ps : Vec<Pattern<TAtom>>, ds : &'a [D], list : &'a D) -> Self {
+        let rest = ps.iter().find_map(|p| match p {
+            Pattern::Rest(name) => Some(name.clone()),
+            _ => None,
+        });
+        ps.retain(|p| ! matches!(p, Pattern::Rest(_)));
+        Bag { ps, rest, ds, used: vec![false; ds.len()], start: 0, list }
+    }
+
+    fn next_unused(&self, start : usize) -> Option<usize> {
+        (start..self.ds.len()).find(|i| ! self.used[*i])
+    }
+}
+
+// A list path with a rest or a repetition, which can match a window of any length.  The windows 
+// are tried in order of where they start and then where they end, beginning with `ds[start..end]`.
+#[derive(Debug)]
+struct Windows<'a, TAtom : Clone + PartialEq, D : Matchable<Atom=TAtom>> {
+    ps : Vec<Pattern<TAtom>>,
+    ds : &'a [D],
+    start : usize,
+    end : usize,
+    list : &'a D,
+}
+
+impl<'a, TAtom : Clone + PartialEq, D : Matchable<Atom=TAtom>> Clone for Windows<'a, TAtom, D> {
+    fn clone(&self) -> Self {
+        Windows { ps: self.ps.clone()
+                , ds: self.ds
+                , start: self.start
+                , end: self.end
+                , list: self.list
+                }
+    }
+}
+
+impl<'a, TAtom : Clone + PartialEq, D : Matchable<Atom=TAtom>> Windows<'a, TAtom, D> {
+    fn new(ps : Vec<Pattern<TAtom>>, ds : &'a [D], list : &'a D) -> Self {
+        Windows { ps, ds, start: 0, end: 0, list }
+    }
+
+    // Note:  every empty window matches the same way, so only the one at the start of the list is used
+    fn next_window(&self, start : usize, end : usize) -> Option<(usize, usize)> {
+        let len = self.ds.len();
+        (start..=len).flat_map(|i| (if i == start { end } else { i }..=len).map(move |j| (i, j)))
+                     .find(|(i, j)| (i < j || *i == 0) && sequence_fits(&self.ps, j - i))
+    }
+}
+
 #[derive(Debug)]
 struct WorkPath<'a, TAtom : Clone + PartialEq, D : Matchable<Atom=TAtom>> {
-    work : Vec<(Pattern<TAtom>, &'a D)>,
+    work : Vec<Task<'a, TAtom, D>>,
     path : Vec<Pattern<TAtom>>,
     nexts : Vec<&'a D>,
 }
@@ -42,11 +241,11 @@ impl<'a, TAtom : Clone + PartialEq, D : Matchable<Atom=TAtom>> WorkPath<'a, TAto
         WorkPath { work: vec![], path, nexts: vec![] }
     }
 
-    pub fn push(&mut self, item : (Pattern<TAtom>, &'a D)) {
-        self.work.push(item);
+    pub fn push(&mut self, task : Task<'a, TAtom, D>) {
+        self.work.push(task);
     }
 
-    pub fn pop(&mut self) -> Option<(Pattern<TAtom>, &'a D)> {
+    pub fn pop(&mut self) -> Option<Task<'a, TAtom, D>> {
         self.work.pop()
     }
 
@@ -71,8 +270,12 @@ impl<'a, TAtom : Clone + PartialEq, D : Matchable<Atom=TAtom>> Work<'a, TAtom, D
         Work { work : vec![WorkPath::empty()] }
     }
 
-    pub fn push(&mut self, item : (Pattern<TAtom>, &'a D)) {
-        self.work.last_mut().unwrap().push(item);
+    pub fn push(&mut self, (pattern, data) : (Pattern<TAtom>, &'a D)) {
+        self.push_task(Task::Match(pattern, data));
+    }
+
+    pub fn push_task(&mut self, task : Task<'a, TAtom, D>) {
+        self.work.last_mut().unwrap().push(task);
     }
 
     pub fn work_finished(&self) -> bool {
@@ -107,33 +310,346 @@ macro_rules! qw {
     };
 }
 
-impl<'a, TAtom : Clone + PartialEq, D : Matchable<Atom=TAtom>> Matches<'a, TAtom, D> {
-    fn pop_current_work(&mut self) -> Option<(Pattern<TAtom>, &'a D)> { 
-        if let Some(ret) = self.current_work.work.last_mut().unwrap().pop() {
-            Some(ret)
+// Note:  the same traversal as `to_lax`, but over `Matchable::children` so that it works for any matchable
+fn pre_order<D : Matchable>(data : &D) -> Vec<&D> {
+    let mut nodes = vec![];
+    let mut stack = vec![data];
+    while let Some(node) = stack.pop() {
+        nodes.push(node);
+        stack.extend(node.children().into_iter().rev());
+    }
+    nodes
+}
+
+fn in_range<D : Matchable>(low : &Bound<D::Atom>, high : &Bound<D::Atom>, atom : &D::Atom) -> bool {
+    let above = match low {
+        Bound::Included(low) => matches!(D::compare(low, atom), Some(Ordering::Less | Ordering::Equal)),
+        Bound::Excluded(low) => matches!(D::compare(low, atom), Some(Ordering::Less)),
+        Bound::Unbounded => true,
+    };
+    let below = match high {
+        Bound::Included(high) => matches!(D::compare(atom, high), Some(Ordering::Less | Ordering::Equal)),
+        Bound::Excluded(high) => matches!(D::compare(atom, high), Some(Ordering::Less)),
+        Bound::Unbounded => true,
+    };
+    above && below
+}
+
+// Note:  templates compare the captured data exactly instead of matching it as a pattern, otherwise
+// an object template would accept any object with extra fields and the comparison would be one sided
+fn same<D : Matchable>(a : &D, b : &D) -> bool {
+    match (a.kind(), b.kind()) {
+        (MatchKind::Atom(a), MatchKind::Atom(b)) => a == b,
+        (MatchKind::Cons(a_name, a_params), MatchKind::Cons(b_name, b_params)) => 
+            a_name == b_name && same_all(a_params, b_params),
+        (MatchKind::List(a), MatchKind::List(b)) => same_all(a, b),
+        // Note:  both sides' fields are checked so that objects with duplicate keys, where `field` 
+        // only sees the first value, still compare the same way in both directions
+        (MatchKind::Object(a), MatchKind::Object(b)) => {
+            let a_fields = D::fields(a);
+            let b_fields = D::fields(b);
+            a_fields.len() == b_fields.len()
+            && a_fields.into_iter().chain(b_fields).all(|k| match (D::field(a, k), D::field(b, k)) {
+                (Some(a), Some(b)) => same(a, b),
+                _ => false,
+            })
+        },
+        _ => false,
+    }
+}
+
+fn same_all<D : Matchable>(a : &[D], b : &[D]) -> bool {
+    a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| same(a, b))
+}
+
+fn is_variable<TAtom : Clone>(p : &Pattern<TAtom>) -> bool {
+    matches!(p, Pattern::Rest(_) | Pattern::Repeat { .. })
+}
+
+fn sequence_fits<TAtom : Clone>(ps : &[Pattern<TAtom>], data_len : usize) -> bool {
+    let min = ps.iter().map(|p| match p {
+        Pattern::Rest(_) => 0,
+        Pattern::Repeat { min, .. } => *min,
+        _ => 1,
+    }).sum::<usize>();
+
+    let max = ps.iter().map(|p| match p {
+        Pattern::Rest(_) => None,
+        Pattern::Repeat { max, .. } => *max,
+        _ => Some(1),
+    }).sum::<Option<usize>>();
+
+    min <= data_len && max.map_or(true, |max| data_len <= max)
+}
+
+fn bag_fits<TAtom : Clone>(ps : &[Pattern<TAtom>], data_len : usize) -> bool {
+    let items = ps.iter().filter(|p| ! matches!(p, Pattern::Rest(_))).count();
+    if items == ps.len() {
+        items == data_len
+    }
+    else {
+        items <= data_len
+    }
+}
+
+// The captures that an alternative adds and the work that it still needs to do.
+type Alternative<'a, TAtom, D> = (MatchMap<'a, D>, Vec<Task<'a, TAtom, D>>);
+
+impl<'a, TAtom : 'a + Clone + PartialEq, D : Matchable<Atom=TAtom>> Matches<'a, TAtom, D> {
+    // Returns false when there is no way for the patterns to line up with the data
+    fn queue_sequence(&mut self, mut ps : Vec<Pattern<TAtom>>, ds : &'a [D], list : &'a D) -> bool {
+        if ps.iter().any(|p| matches!(p, Pattern::Repeat { .. })) {
+            self.current_work.push_task(Task::Sequence { ps, ds, list });
+            return true;
         }
-        else if self.current_work.work.last().unwrap().nexts.len() != 0 { 
-            let mut nexts = std::mem::replace(&mut self.current_work.work.last_mut().unwrap().nexts, vec![]);
-            nexts.reverse();
 
-            let pattern = self.current_work.work.last_mut().unwrap().path.pop().unwrap();
+        match ps.iter().position(|p| matches!(p, Pattern::Rest(_))) {
+            Some(index) => {
+                let after = ps.split_off(index + 1);
+                let rest = ps.pop().unwrap();
+
+                let (before_ds, ds) = ds.split_at(ps.len());
+                let (rest_ds, after_ds) = ds.split_at(ds.len() - after.len());
+
+                qw!(self.current_work, after, after_ds);
+                qw!(self.current_work, ps, before_ds);
+
+                if let Pattern::Rest(Some(name)) = rest {
+                    self.matches.push((name, Capture::Slice(rest_ds)));
+                }
+            },
+            None => {
+                qw!(self.current_work, ps, ds);
+            },
+        }
+        true
+    }
 
-            let first = nexts.pop().unwrap();
+    // Returns false when there are no alternatives
+    fn queue_alternatives(&mut self, mut alternatives : Vec<Alternative<'a, TAtom, D>>, list : &'a D) -> bool {
+        if alternatives.len() == 0 {
+            return false;
+        }
 
-            for next in nexts.into_iter() {
-                let mut work = self.current_work.clone();
-                work.push((pattern.clone(), next));
-                self.future_work.push((self.matches.clone(), work));
+        // Note:  an alternative with no work left would look like a finished matcher, so 
+        // every alternative gets at least a wild match against the list
+        for (_, work) in alternatives.iter_mut() {
+            work.push(Task::Match(Pattern::Wild, list));
+        }
+
+        let (captures, work) = alternatives.remove(0);
+        for (other_captures, other_work) in alternatives.into_iter().rev() {
+            let mut matches = self.matches.clone();
+            matches.extend(other_captures);
+            let mut current_work = self.current_work.clone();
+            for task in other_work.into_iter().rev() {
+                current_work.push_task(task);
             }
+            self.future_work.push((matches, current_work));
+        }
 
-            Some((pattern, first))
+        self.matches.extend(captures);
+        for task in work.into_iter().rev() {
+            self.current_work.push_task(task);
         }
-        else if self.current_work.work.len() > 1 {
-            self.current_work.work.pop();
-            self.pop_current_work()
+        true
+    }
+
+    // Returns false when the patterns can't line up with the data
+    fn step_sequence(&mut self, mut ps : Vec<Pattern<TAtom>>, ds : &'a [D], list : &'a D) -> bool {
+        if ps.len() == 0 {
+            return ds.len() == 0;
+        }
+
+        match ps.remove(0) {
+            // Note:  shorter rests are tried first
+            Pattern::Rest(name) => {
+                let alternatives = (0..=ds.len()).filter(|len| sequence_fits(&ps, ds.len() - len)).map(|len| {
+                    let captures = match &name {
+                        Some(name) => vec![(name.clone(), Capture::Slice(&ds[..len]))],
+                        None => vec![],
+                    };
+                    (captures, vec![Task::Sequence { ps: ps.clone(), ds: &ds[len..], list }])
+                }).collect();
+                self.queue_alternatives(alternatives, list)
+            },
+            Pattern::Repeat { pattern, min, max } => {
+                let repetition = Repetition { pattern: *pattern, min, max, items: vec![], ps, ds, list };
+                self.step_repetition(repetition)
+            },
+            p if ds.len() != 0 => {
+                self.current_work.push_task(Task::Sequence { ps, ds: &ds[1..], list });
+                self.current_work.push((p, &ds[0]));
+                true
+            },
+            _ => false,
+        }
+    }
+
+    // Note:  each step either matches one more item or stops the repetition.  Longer repetitions are 
+    // tried first.
+    fn step_repetition(&mut self, repetition : Repetition<'a, TAtom, D>) -> bool {
+        let mut alternatives = vec![];
+
+        if repetition.can_continue() {
+            for item in self.sub_matches(&repetition.pattern, &repetition.ds[0]) {
+                let mut next = repetition.clone();
+                next.items.push(item);
+                next.ds = &repetition.ds[1..];
+                alternatives.push((vec![], vec![Task::Repetition(next)]));
+            }
+        }
+
+        if repetition.can_stop() {
+            let captures = repetition.captures();
+            let Repetition { ps, ds, list, .. } = repetition;
+            alternatives.push((captures, vec![Task::Sequence { ps, ds, list }]));
+        }
+
+        self.queue_alternatives(alternatives, repetition.list)
+    }
+
+    // Note:  earlier patterns take earlier items first.  The other items that a pattern could take are 
+    // tried later by the same bag starting one item further along.  A rest gets the remaining items in 
+    // the order that they have in the list.
+    fn step_bag(&mut self, mut bag : Bag<'a, TAtom, D>) -> bool {
+        if bag.ps.len() == 0 {
+            if let Some(Some(name)) = &bag.rest {
+                let remaining = (0..bag.ds.len()).filter(|i| ! bag.used[*i]).map(|i| Capture::Node(&bag.ds[i])).collect();
+                self.matches.push((name.clone(), Capture::Seq(remaining)));
+            }
+            return true;
+        }
+
+        let index = match bag.next_unused(bag.start) {
+            Some(index) => index,
+            None => return false,
+        };
+
+        if bag.next_unused(index + 1).is_some() {
+            let mut work = self.current_work.clone();
+            let mut other = bag.clone();
+            other.start = index + 1;
+            work.push_task(Task::Bag(other));
+            self.future_work.push((self.matches.clone(), work));
+        }
+
+        let p = bag.ps.remove(0);
+        bag.used[index] = true;
+        bag.start = 0;
+        let d = &bag.ds[index];
+        self.current_work.push_task(Task::Bag(bag));
+        self.current_work.push((p, d));
+        true
+    }
+
+    // Note:  like a bag, the next window is queued as future work before this one is matched
+    fn step_windows(&mut self, windows : Windows<'a, TAtom, D>) -> bool {
+        let (start, end) = match windows.next_window(windows.start, windows.end) {
+            Some(window) => window,
+            None => return false,
+        };
+
+        if let Some((next_start, next_end)) = windows.next_window(start, end + 1) {
+            let mut work = self.current_work.clone();
+            let mut other = windows.clone();
+            other.start = next_start;
+            other.end = next_end;
+            work.push_task(Task::Windows(other));
+            self.future_work.push((self.matches.clone(), work));
+        }
+
+        let Windows { ps, ds, list, .. } = windows;
+        // Note:  so that a window with nothing left to match isn't mistaken for a finished matcher
+        self.current_work.push((Pattern::Wild, list));
+        self.queue_sequence(ps, &ds[start..end], list)
+    }
+
+    fn backtrack(&mut self) -> bool {
+        if let Some((new_matches, new_work)) = self.future_work.pop() {
+            self.current_work = new_work;
+            self.matches = new_matches;
+            true
         }
         else {
-            None
+            self.current_work = Work::new();
+            self.matches = vec![];
+            false
+        }
+    }
+
+    // All of the ways that the pattern matches the data, but only with the captures that it adds
+    fn sub_matches(&self, pattern : &Pattern<TAtom>, data : &'a D) -> Vec<MatchMap<'a, D>> {
+        let mut current_work = Work::new();
+        current_work.push((pattern.clone(), data));
+
+        let before = self.matches.len();
+        let sub = Matches { matches: self.matches.clone(), current_work, future_work: vec![] };
+        sub.map(|m| m.into_iter().skip(before).collect()).collect()
+    }
+
+    fn sub_match_fails(&self, pattern : &Pattern<TAtom>, data : &'a D) -> bool {
+        let mut current_work = Work::new();
+        current_work.push((pattern.clone(), data));
+
+        // Note:  the sub match starts with the current captures so that templates can reference them
+        let mut sub = Matches { matches: self.matches.clone(), current_work, future_work: vec![] };
+        sub.next().is_none()
+    }
+
+    fn pop_current_work(&mut self) -> Option<(Pattern<TAtom>, &'a D)> { 
+        loop {
+            if let Some(task) = self.current_work.work.last_mut().unwrap().pop() {
+                // Note:  a sequence step that fails hands back a failing pattern so that the match backtracks
+                match task {
+                    Task::Match(pattern, data) => { return Some((pattern, data)); },
+                    Task::Sequence { ps, ds, list } => {
+                        if ! self.step_sequence(ps, ds, list) {
+                            return Some((Pattern::Fail, list));
+                        }
+                    },
+                    Task::Repetition(repetition) => {
+                        let list = repetition.list;
+                        if ! self.step_repetition(repetition) {
+                            return Some((Pattern::Fail, list));
+                        }
+                    },
+                    Task::Bag(bag) => {
+                        let list = bag.list;
+                        if ! self.step_bag(bag) {
+                            return Some((Pattern::Fail, list));
+                        }
+                    },
+                    Task::Windows(windows) => {
+                        let list = windows.list;
+                        if ! self.step_windows(windows) {
+                            return Some((Pattern::Fail, list));
+                        }
+                    },
+                }
+            }
+            else if self.current_work.work.last().unwrap().nexts.len() != 0 { 
+                let mut nexts = std::mem::replace(&mut self.current_work.work.last_mut().unwrap().nexts, vec![]);
+                nexts.reverse();
+
+                let pattern = self.current_work.work.last_mut().unwrap().path.pop().unwrap();
+
+                let first = nexts.pop().unwrap();
+
+                for next in nexts.into_iter() {
+                    let mut work = self.current_work.clone();
+                    work.push((pattern.clone(), next));
+                    self.future_work.push((self.matches.clone(), work));
+                }
+
+                return Some((pattern, first));
+            }
+            else if self.current_work.work.len() > 1 {
+                self.current_work.work.pop();
+            }
+            else {
+                return None;
+            }
         }
     }
 }
@@ -148,16 +664,44 @@ impl<'a, TAtom : 'a + Clone + PartialEq, D : Matchable<Atom=TAtom>> Iterator for
 
         while let Some((pattern, matchable)) = self.pop_current_work() {
             match (pattern, matchable.kind()) {
-                (Pattern::CaptureVar(name), _) => { self.matches.push((name.into(), matchable)); },
+                (Pattern::CaptureVar(name), _) => { self.matches.push((name.into(), Capture::Node(matchable))); },
                 (Pattern::ExactList(ps), MatchKind::List(ds)) if ps.len() == 0 && ds.len() == 0 => { /* pass */ },
-                (Pattern::ExactList(ps), MatchKind::List(ds)) if ps.len() == ds.len() => {
-                    qw!(self.current_work, ps, ds);
+                (Pattern::ExactList(ps), MatchKind::List(ds)) if sequence_fits(&ps, ds.len()) => {
+                    if ! self.queue_sequence(ps, ds, matchable) && ! self.backtrack() {
+                        return None;
+                    }
                 },
 
                 (Pattern::Cons {name: pname, params: pparams}, MatchKind::Cons(dname, dparams)) 
-                    if pname == dname.into() && pparams.len() == dparams.len() => {
+                    if pname == dname.into() && sequence_fits(&pparams, dparams.len()) => {
 
-                    qw!(self.current_work, pparams, dparams);
+                    if ! self.queue_sequence(pparams, dparams, matchable) && ! self.backtrack() {
+                        return None;
+                    }
+                },
+
+                (Pattern::AnyCons {name: pname, params: pparams}, MatchKind::Cons(dname, dparams)) 
+                    if pname.matches(dname) && sequence_fits(&pparams, dparams.len()) => {
+
+                    if let ConsName::Capture(v) = pname {
+                        self.matches.push((v, Capture::Name(dname)));
+                    }
+
+                    if ! self.queue_sequence(pparams, dparams, matchable) && ! self.backtrack() {
+                        return None;
+                    }
+                },
+
+                (Pattern::Object(ps), MatchKind::Object(o)) if ps.iter().all(|(k, _)| D::field(o, k).is_some()) => {
+                    for (k, p) in ps.into_iter().rev() {
+                        let d = D::field(o, &k).unwrap();
+                        self.current_work.push((p, d));
+                    }
+                },
+
+                // Note:  with a rest or a repetition the window can be any length, so every start and end is tried
+                (Pattern::ListPath(ps), MatchKind::List(ds)) if ps.iter().any(is_variable) => {
+                    self.current_work.push_task(Task::Windows(Windows::new(ps, ds, matchable)));
                 },
 
                 (Pattern::ListPath(ps), MatchKind::List(_)) if ps.len() == 0 => { /* pass */ },
@@ -175,15 +719,38 @@ impl<'a, TAtom : 'a + Clone + PartialEq, D : Matchable<Atom=TAtom>> Iterator for
                     qw!(self.current_work, ps, target);
                 },
 
+                (Pattern::Bag(ps), MatchKind::List(ds)) if bag_fits(&ps, ds.len()) => {
+                    self.current_work.push_task(Task::Bag(Bag::new(ps, ds, matchable)));
+                },
+
                 (Pattern::Wild, _) => { /* pass */ },
                 (Pattern::Atom(p), MatchKind::Atom(m)) if p == *m => { /* pass */ },
+                (Pattern::Range(low, high), MatchKind::Atom(m)) if in_range::<D>(&low, &high, m) => { /* pass */ },
+                (Pattern::Kind(k), kind) if k.matches(&kind) => { /* pass */ },
+                (Pattern::Predicate(p), MatchKind::Atom(m)) if D::atom_text(m).map_or(false, |text| p.matches(text)) => { /* pass */ },
 
-                (Pattern::TemplateVar(var), _) => {
-                    let (_, d) = self.matches.iter().find(|(k, _)| k == &var ).unwrap();
-                    let p = d.to_pattern();
-                    self.current_work.push((p, matchable));
+                (Pattern::As(name, p), _) => { 
+                    self.matches.push((name, Capture::Node(matchable)));
+                    self.current_work.push((*p, matchable));
                 },
 
+                // Note:  nodes are visited in pre-order, so a node's results come before its children's
+                (Pattern::Descendant(p), _) => {
+                    let mut nodes = pre_order(matchable);
+                    let first = nodes.remove(0);
+
+                    for node in nodes.into_iter().rev() {
+                        let mut work = self.current_work.clone();
+                        work.push(((*p).clone(), node));
+                        self.future_work.push((self.matches.clone(), work));
+                    }
+
+                    self.current_work.push((*p, first));
+                },
+
+                (Pattern::TemplateVar(var), _) 
+                    if self.matches.iter().find(|(k, _)| k == &var).and_then(|(_, d)| d.node()).map_or(false, |d| same(d, matchable)) => { /* pass */ },
+
                 (Pattern::And(a, b), _) => {
                     self.current_work.push((*b, matchable));
                     self.current_work.push((*a, matchable));
@@ -198,6 +765,8 @@ impl<'a, TAtom : 'a + Clone + PartialEq, D : Matchable<Atom=TAtom>> Iterator for
                     self.current_work.path(ps.clone(), matchable);   
                 },
 
+                (Pattern::Not(a), _) if self.sub_match_fails(&a, matchable) => { /* pass */ },
+
                 (Pattern::Or(a, b), _) => {
                     let mut work = self.current_work.clone();
                     work.push((*b, matchable));
@@ -205,15 +774,15 @@ impl<'a, TAtom : 'a + Clone + PartialEq, D : Matchable<Atom=TAtom>> Iterator for
                     self.current_work.push((*a, matchable));
                 },
 
+                (Pattern::Fail, _) => {
+                    if ! self.backtrack() {
+                        return None;
+                    }
+                },
+
                 _ => { 
                     // This match failed
-                    if let Some((new_matches, new_work)) = self.future_work.pop() {
-                        self.current_work = new_work;
-                        self.matches = new_matches;
-                    }
-                    else {
-                        self.current_work = Work::new();
-                        self.matches = vec![];
+                    if ! self.backtrack() {
                         return None;
                     }
                 },
@@ -231,3 +800,266 @@ impl<'a, TAtom : 'a + Clone + PartialEq, D : Matchable<Atom=TAtom>> Iterator for
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::data::*;
+
+    #[derive(Debug, PartialEq)]
+    enum Node {
+        Scalar(SymStr),
+        Array(Vec<Node>),
+        Record(Vec<(Box<str>, Node)>),
+    }
+
+    impl Matchable for Node {
+        type Atom = SymStr;
+        type Object = Vec<(Box<str>, Node)>;
+
+        fn kind(&self) -> MatchKind<Self> {
+            match self {
+                Node::Scalar(a) => MatchKind::Atom(a),
+                Node::Array(ns) => MatchKind::List(ns),
+                Node::Record(fs) => MatchKind::Object(fs),
+            }
+        }
+
+        fn to_pattern(&self) -> Pattern<Self::Atom> {
+            match self {
+                Node::Scalar(a) => Pattern::Atom(a.clone()),
+                Node::Array(ns) => Pattern::ExactList(ns.iter().map(|x| x.to_pattern()).collect()),
+                Node::Record(fs) => Pattern::Object(fs.iter().map(|(k, v)| (k.clone(), v.to_pattern())).collect()),
+            }
+        }
+
+        fn field<'a>(object : &'a Self::Object, key : &str) -> Option<&'a Self> {
+            object.iter().find(|(k, _)| **k == *key).map(|(_, v)| v)
+        }
+
+        fn fields<'a>(object : &'a Self::Object) -> Vec<&'a str> {
+            object.iter().map(|(k, _)| &**k).collect()
+        }
+
+        fn compare(a : &SymStr, b : &SymStr) -> Option<Ordering> {
+            a.partial_cmp(b)
+        }
+    }
+
+    // An atom without any ordering
+    #[derive(Debug, Clone, PartialEq)]
+    struct Tag(Box<str>);
+
+    #[derive(Debug, PartialEq)]
+    enum Tagged {
+        Leaf(Tag),
+        Many(Vec<Tagged>),
+    }
+
+    impl Matchable for Tagged {
+        type Atom = Tag;
+        type Object = ();
+
+        fn kind(&self) -> MatchKind<Self> {
+            match self {
+                Tagged::Leaf(t) => MatchKind::Atom(t),
+                Tagged::Many(ts) => MatchKind::List(ts),
+            }
+        }
+
+        fn to_pattern(&self) -> Pattern<Self::Atom> {
+            match self {
+                Tagged::Leaf(t) => Pattern::Atom(t.clone()),
+                Tagged::Many(ts) => Pattern::ExactList(ts.iter().map(|x| x.to_pattern()).collect()),
+            }
+        }
+    }
+
+    #[test]
+    fn should_match_atoms_without_ordering() {
+        let data = Tagged::Many(vec![Tagged::Leaf(Tag("a".into())), Tagged::Leaf(Tag("b".into()))]);
+
+        let pattern = check_pattern(Pattern::ExactList(vec![Pattern::Atom(Tag("a".into())), Pattern::CaptureVar("x".into())])).unwrap();
+        let results = pattern_match(&pattern, &data).collect::<Vec<_>>();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0][0].1.node(), Some(&Tagged::Leaf(Tag("b".into()))));
+
+        let pattern = check_pattern(Pattern::ExactList(vec![Pattern::Range(Bound::Included(Tag("a".into())), Bound::Unbounded), Pattern::Wild])).unwrap();
+        assert_eq!(pattern_match(&pattern, &data).count(), 0);
+    }
+
+    fn sym(s : &str) -> Node { Node::Scalar(SymStr::Symbol(s.into())) }
+
+    fn person(name : &str, age : i64) -> Node {
+        Node::Record(vec![ ("name".into(), sym(name))
+                         , ("age".into(), Node::Scalar(SymStr::Int(age)))
+                         , ("kind".into(), sym("person"))
+                         ])
+    }
+
+    fn run<'a>(pattern : &str, data : &'a Node) -> Vec<MatchMap<'a, Node>> {
+        let pattern = check_pattern(pattern.parse::<Pattern<SymStr>>().unwrap()).unwrap();
+        pattern_match(&pattern, data).collect()
+    }
+
+    #[test]
+    fn should_match_object_fields_in_any_order() {
+        let data = person("alice", 30);
+        let results = run("{ age: a, name: n }", &data);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0], vec![("a".into(), Capture::Node(&Node::Scalar(SymStr::Int(30)))), ("n".into(), Capture::Node(&sym("alice")))]);
+    }
+
+    #[test]
+    fn should_match_empty_object_pattern() {
+        let data = person("alice", 30);
+        let results = run("{}", &data);
+        assert_eq!(results.len(), 1);
+    }
+
+    #[test]
+    fn should_not_match_object_with_missing_field() {
+        let data = person("alice", 30);
+        let results = run("{ name: n, email: e }", &data);
+        assert_eq!(results.len(), 0);
+    }
+
+    #[test]
+    fn should_not_match_object_with_field_mismatch() {
+        let data = person("alice", 30);
+        let results = run("{ name: :bob }", &data);
+        assert_eq!(results.len(), 0);
+    }
+
+    #[test]
+    fn should_not_match_object_pattern_against_list() {
+        let data = Node::Array(vec![sym("name")]);
+        let results = run("{ name: n }", &data);
+        assert_eq!(results.len(), 0);
+    }
+
+    #[test]
+    fn should_match_objects_in_list_path() {
+        let data = Node::Array(vec![person("alice", 30), person("bob", 15), person("carol", 50)]);
+        let results = run("[| { name: n, age: >= 18 } |]", &data);
+        assert_eq!(results, vec![ vec![("n".into(), Capture::Node(&sym("alice")))]
+                                , vec![("n".into(), Capture::Node(&sym("carol")))]
+                                ]);
+    }
+
+    #[test]
+    fn should_match_template_of_object() {
+        let data = Node::Array(vec![person("alice", 30), person("alice", 30)]);
+        let results = run("[a, %a]", &data);
+        assert_eq!(results.len(), 1);
+    }
+
+    #[test]
+    fn should_list_fields() {
+        let data = person("alice", 30);
+        match data.kind() {
+            MatchKind::Object(o) => assert_eq!(Node::fields(o), vec!["name", "age", "kind"]),
+            _ => panic!("expected object"),
+        }
+    }
+
+    fn run_nonlinear<'a>(pattern : &str, data : &'a Data) -> Vec<MatchMap<'a, Data>> {
+        let pattern = check_nonlinear_pattern(pattern.parse::<Pattern<SymStr>>().unwrap()).unwrap();
+        pattern_match(&pattern, data).collect()
+    }
+
+    #[test]
+    fn should_match_repeated_capture_when_equal() {
+        let data = "[cons(:a, [1, 2]), cons(:a, [1, 2.0])]".parse::<Data>().unwrap();
+        let results = run_nonlinear("[x, x]", &data);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].len(), 1);
+        assert_eq!(results[0][0].0, "x".into());
+    }
+
+    #[test]
+    fn should_not_match_repeated_capture_when_not_equal() {
+        let data = "[cons(:a, [1, 2]), cons(:a, [1, 3])]".parse::<Data>().unwrap();
+        let results = run_nonlinear("[x, x]", &data);
+        assert_eq!(results.len(), 0);
+    }
+
+    #[test]
+    fn should_not_match_repeated_capture_when_object_has_extra_field() {
+        let data = "[{ a: 1 }, { a: 1, b: 2 }]".parse::<Data>().unwrap();
+        assert_eq!(run_nonlinear("[x, x]", &data).len(), 0);
+
+        let data = "[{ a: 1, b: 2 }, { a: 1 }]".parse::<Data>().unwrap();
+        assert_eq!(run_nonlinear("[x, x]", &data).len(), 0);
+    }
+
+    #[test]
+    fn should_compare_objects_with_duplicate_keys_the_same_both_ways() {
+        fn record(fields : Vec<(&str, i64)>) -> Data {
+            Data::Record(fields.into_iter().map(|(k, v)| (k.into(), Data::SymStr(SymStr::Int(v)))).collect())
+        }
+
+        let duplicate = record(vec![("a", 1), ("a", 2)]);
+        let other = record(vec![("a", 1), ("b", 2)]);
+        assert!(! same(&duplicate, &other));
+        assert!(! same(&other, &duplicate));
+        assert!(same(&duplicate, &record(vec![("a", 1), ("a", 3)])));
+    }
+
+    #[test]
+    fn should_match_repeated_capture_when_object_fields_reordered() {
+        let data = "[{ a: 1, b: [:c] }, { b: [:c], a: 1.0 }]".parse::<Data>().unwrap();
+        assert_eq!(run_nonlinear("[x, x]", &data).len(), 1);
+    }
+
+    #[test]
+    fn should_find_equal_pairs_with_repeated_capture() {
+        let data = "[:a, :b, :b, :c, :c]".parse::<Data>().unwrap();
+        let results = run_nonlinear("[| x, x |]", &data);
+        let xs = results.iter().map(|r| r[0].1.node().unwrap()).collect::<Vec<_>>();
+        assert_eq!(xs, vec![&":b".parse::<Data>().unwrap(), &":c".parse::<Data>().unwrap()]);
+    }
+
+    #[test]
+    fn should_find_first_repetition_match_without_trying_every_combination() {
+        let data = format!("[{}]", vec![":a"; 64].join(", ")).parse::<Data>().unwrap();
+        let pattern = check_pattern("[x.or(x)*, ..]".parse::<Pattern<SymStr>>().unwrap()).unwrap();
+        let mut results = pattern_match(&pattern, &data);
+        let first = results.next().unwrap();
+        assert_eq!(first[0].1.seq().unwrap().len(), 64);
+        assert_eq!(results.next().unwrap()[0].1.seq().unwrap().len(), 64);
+    }
+
+    #[test]
+    fn should_find_first_bag_match_without_trying_every_assignment() {
+        let names = (0..12).map(|i| format!("x{i}")).collect::<Vec<_>>();
+        let data = format!("[{}]", (0..12).map(|i| i.to_string()).collect::<Vec<_>>().join(", ")).parse::<Data>().unwrap();
+        let pattern = check_pattern(format!("#[{}]", names.join(", ")).parse::<Pattern<SymStr>>().unwrap()).unwrap();
+        let mut results = pattern_match(&pattern, &data);
+
+        let first = results.next().unwrap();
+        let values = first.iter().map(|(_, c)| format!("{}", c.node().unwrap())).collect::<Vec<_>>();
+        assert_eq!(values, (0..12).map(|i| i.to_string()).collect::<Vec<_>>());
+
+        let second = results.next().unwrap();
+        assert_eq!(format!("{}", second[10].1.node().unwrap()), "11");
+        assert_eq!(format!("{}", second[11].1.node().unwrap()), "10");
+    }
+
+    #[test]
+    fn should_queue_list_path_windows_one_at_a_time() {
+        let data = format!("[{}]", (0..100).map(|i| i.to_string()).collect::<Vec<_>>().join(", ")).parse::<Data>().unwrap();
+        let pattern = check_pattern("[| ..x |]".parse::<Pattern<SymStr>>().unwrap()).unwrap();
+        let mut results = pattern_match(&pattern, &data);
+
+        let first = results.next().unwrap();
+        assert_eq!(first[0].1.slice().unwrap().len(), 0);
+        assert!(results.future_work.len() <= 1);
+
+        let second = results.next().unwrap();
+        assert_eq!(second[0].1.slice().unwrap().len(), 1);
+        assert!(results.future_work.len() <= 1);
+
+        assert_eq!(results.count(), 5051 - 2);
+    }
+}
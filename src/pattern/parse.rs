@@ -1,4 +1,5 @@
 
+use std::ops::Bound;
 use std::str::Chars;
 use renounce::*;
 
@@ -6,64 +7,69 @@ use crate::data::*;
 use crate::parsing::*;
 use super::data::*;
 
-#[derive(Debug)]
-pub struct E(Box<str>);
-
-impl std::fmt::Display for E {
-    fn fmt(&self, f : &mut std::fmt::Formatter) -> std::fmt::Result {
-        match self {
-            E(s) => write!(f, "{}", s),
-        }
-    }
-}
-
-impl std::error::Error for E { }
+pub use crate::error::SyntaxError;
 
 
 impl std::str::FromStr for Pattern<SymStr> {
-    type Err = Box<dyn std::error::Error>;  
+    type Err = crate::error::ParseError;
 
     fn from_str(s : &str) -> Result<Self, Self::Err> {
-        let mut cs = s.chars();
-        let result = parse(&mut cs);
-        match result {
-            Ok(v) => Ok(v),
-            Err(ParseError::Fatal(x)) => Err(Box::new(E(format!("Error Trace: {:?}\nAt: {}", x, cs.collect::<String>()).into()))),
-            Err(ParseError::Error) => Err(Box::new(E("Not a Pattern".into()))),
-        }
+        run_parser(s, parse, "pattern")
     }
 }
 
+expecting!(Unexpected, expect_pattern = parse_pattern : Pattern<SymStr> => "pattern");
+expecting!(Unexpected, expect_bound = parse_bound : SymStr => "number", "string", "symbol");
+
 fn parse(input : &mut Chars) -> Result<Pattern<SymStr>, ParseError> {
     parser!(input => {
-        pattern <= ! parse_pattern;
+        pattern <= ! expect_pattern;
         ! end;
         select pattern 
     })
 }
 
-fn parse_pattern(input : &mut Chars) -> Result<Pattern<SymStr>, ParseError> {
-    fn options(input : &mut Chars) -> Result<Pattern<SymStr>, ParseError> {
-        alt!(input => parse_cons; 
-                      parse_list_path;
-                      parse_list; 
-                      parse_wild;
-                      // Note:  parse capture variable needs to happen after parse wild
-                      parse_capture_var;
-                      parse_path;
-                      parse_path_next;
-                      parse_symbol;
-                      parse_string_pattern;
-                      parse_template_variable)
-    }
+fn parse_option(input : &mut Chars) -> Result<Pattern<SymStr>, ParseError> {
+    // Note:  parse any cons needs to happen before parse group, parse as, and parse wild
+    alt!(input => parse_any_cons;
+                  parse_group;
+                  // Note:  parse as needs to happen before parse wild and parse capture variable
+                  parse_as;
+                  parse_descendant;
+                  parse_cons; 
+                  parse_list_path;
+                  parse_list; 
+                  parse_bag;
+                  parse_predicate;
+                  parse_kind_test;
+                  parse_unbounded_range;
+                  parse_wild;
+                  // Note:  parse capture variable needs to happen after parse wild
+                  parse_capture_var;
+                  parse_path;
+                  // Note:  parse object needs to happen after parse path
+                  parse_object;
+                  parse_path_next;
+                  parse_fail;
+                  parse_rest;
+                  // Note:  parse range needs to happen before parse symbol, parse string pattern, and 
+                  // parse number
+                  parse_range;
+                  parse_symbol;
+                  parse_string_pattern;
+                  parse_comparison;
+                  parse_number_pattern;
+                  parse_template_variable)
+}
 
+fn parse_pattern(input : &mut Chars) -> Result<Pattern<SymStr>, ParseError> {
     fn end_options(input : &mut Chars) -> Result<EndCombinator, ParseError> {
-        alt!(input => parse_and; parse_or)
+        alt!(input => parse_and; parse_or; parse_not)
     }
 
     parser!(input => {
         _before_clear <= parse_whitespace;
-        pattern <= options;
+        pattern <= parse_option;
         _after_clear <= parse_whitespace;
         ends <= * end_options;
         _after_end_clear <= parse_whitespace;
@@ -71,6 +77,8 @@ fn parse_pattern(input : &mut Chars) -> Result<Pattern<SymStr>, ParseError> {
             |p, ec| match ec { 
                 EndCombinator::And(e) => Pattern::And(Box::new(p), Box::new(e)),
                 EndCombinator::Or(e) => Pattern::Or(Box::new(p), Box::new(e)),
+                EndCombinator::Not(e) if matches!(p, Pattern::Wild) => Pattern::Not(Box::new(e)),
+                EndCombinator::Not(e) => Pattern::And(Box::new(p), Box::new(Pattern::Not(Box::new(e)))),
             })
     })
 }
@@ -78,12 +86,143 @@ fn parse_pattern(input : &mut Chars) -> Result<Pattern<SymStr>, ParseError> {
 enum EndCombinator {
     And(Pattern<SymStr>),
     Or(Pattern<SymStr>),
+    Not(Pattern<SymStr>),
+}
+
+// Note:  like `@`, `**` binds tighter than `.and`, `.or`, and `.not`
+fn parse_descendant(input : &mut Chars) -> Result<Pattern<SymStr>, ParseError> {
+    pat!(parse_star: char => () = '*' => ());
+    expecting!(Unexpected, expect_option = parse_option : Pattern<SymStr> => "pattern");
+
+    parser!(input => {
+        _star_0 <= parse_star;
+        _star_1 <= parse_star;
+        _clear <= parse_whitespace;
+        pattern <= ! expect_option;
+        select Pattern::Descendant(Box::new(pattern))
+    })
+}
+
+// Note:  repetition only makes sense for the items of a list, so it is parsed here instead of 
+// in parse pattern
+fn parse_element(input : &mut Chars) -> Result<Pattern<SymStr>, ParseError> {
+    parser!(input => {
+        pattern <= parse_pattern;
+        repetition <= ? parse_repetition;
+        _clear <= parse_whitespace;
+        select match repetition {
+            Some((min, max)) => Pattern::Repeat { pattern: Box::new(pattern), min, max },
+            None => pattern,
+        }
+    })
+}
+
+fn parse_repetition(input : &mut Chars) -> Result<(usize, Option<usize>), ParseError> {
+    pat!(parse_star: char => (usize, Option<usize>) = '*' => (0, None));
+    pat!(parse_plus: char => (usize, Option<usize>) = '+' => (1, None));
+    pat!(parse_question: char => (usize, Option<usize>) = '?' => (0, Some(1)));
+    pat!(parse_l_curl: char => () = '{' => ());
+    pat!(parse_r_curl: char => () = '}' => ());
+    pat!(parse_comma: char => () = ',' => ());
+
+    fn parse_digits(input : &mut Chars) -> Result<String, ParseError> {
+        parser!(input => {
+            first <= parse_digit;
+            rest <= * parse_digit;
+            select {
+                let mut rest = rest;
+                rest.insert(0, first);
+                rest.into_iter().collect::<String>()
+            }
+        })
+    }
+
+    fn parse_count(input : &mut Chars) -> Result<usize, ParseError> {
+        parser!(input => {
+            text <= parse_digits;
+            where text.parse::<usize>().is_ok();
+            select text.parse::<usize>().unwrap()
+        })
+    }
+
+    expecting!(Unexpected, expect_count = parse_count : usize => "count");
+    expecting!(Unexpected, expect_r_curl = parse_r_curl : () => "`,`", "`}`");
+
+    fn parse_upper(input : &mut Chars) -> Result<Option<usize>, ParseError> {
+        parser!(input => {
+            _comma <= parse_comma;
+            _clear <= parse_whitespace;
+            max <= ? parse_count;
+            _clear_2 <= parse_whitespace;
+            select max
+        })
+    }
+
+    fn parse_bounds(input : &mut Chars) -> Result<(usize, Option<usize>), ParseError> {
+        parser!(input => {
+            _l_curl <= parse_l_curl;
+            _clear_0 <= parse_whitespace;
+            min <= ! expect_count;
+            _clear_1 <= parse_whitespace;
+            upper <= ? parse_upper;
+            _r_curl <= ! expect_r_curl;
+            select match upper {
+                Some(max) => (min, max),
+                None => (min, Some(min)),
+            }
+        })
+    }
+
+    alt!(input => parse_star; parse_plus; parse_question; parse_bounds)
+}
+
+fn parse_group(input : &mut Chars) -> Result<Pattern<SymStr>, ParseError> {
+    pat!(parse_l_paren: char => () = '(' => ());
+    pat!(parse_r_paren: char => () = ')' => ());
+    expecting!(UnbalancedBrackets, expect_r_paren = parse_r_paren : () => "`)`");
+
+    parser!(input => {
+        _paren_l <= parse_l_paren;
+        pattern <= ! expect_pattern;
+        _paren_r <= ! expect_r_paren;
+        select pattern
+    })
+}
+
+// Note:  the pattern after `@` doesn't take `.and`, `.or`, or `.not`, so `x @ a.or(b)` 
+// binds `x` to whatever `a` matched.  Use `x @ (a.or(b))` to bind around the whole thing.
+fn parse_as(input : &mut Chars) -> Result<Pattern<SymStr>, ParseError> {
+    pat!(parse_at: char => () = '@' => ());
+    expecting!(Unexpected, expect_option = parse_option : Pattern<SymStr> => "pattern");
+
+    fn parse_plain_name(input : &mut Chars) -> Result<Box<str>, ParseError> {
+        parser!(input => {
+            name <= parse_word;
+            where *name != *"_";
+            select name
+        })
+    }
+
+    fn parse_as_name(input : &mut Chars) -> Result<Box<str>, ParseError> {
+        alt!(input => parse_plain_name; parse_quoted_name)
+    }
+
+    parser!(input => {
+        name <= parse_as_name;
+        _clear_0 <= parse_whitespace;
+        _at <= parse_at;
+        _clear_1 <= parse_whitespace;
+        pattern <= ! expect_option;
+        select Pattern::As(name, Box::new(pattern))
+    })
 }
 
 fn parse_or(input : &mut Chars) -> Result<EndCombinator, ParseError> {
     pat!(parse_dot: char => () = '.' => ());
     pat!(parse_l_paren: char => () = '(' => ());
     pat!(parse_r_paren: char => () = ')' => ());
+    expecting!(Unexpected, expect_l_paren = parse_l_paren : () => "`(`");
+    expecting!(UnbalancedBrackets, expect_r_paren = parse_r_paren : () => "`)`");
 
     fn parse_and(input : &mut Chars) -> Result<(), ParseError> {
         parser!(input => {
@@ -99,9 +238,9 @@ fn parse_or(input : &mut Chars) -> Result<EndCombinator, ParseError> {
         _ws1 <= parse_whitespace;
         _and <= parse_and;
         _ws2 <= parse_whitespace;
-        _paren_l <= ! parse_l_paren;
-        pattern <= ! parse_pattern;
-        _paren_r <= ! parse_r_paren; 
+        _paren_l <= ! expect_l_paren;
+        pattern <= ! expect_pattern;
+        _paren_r <= ! expect_r_paren; 
         _ws3 <= parse_whitespace;
         select EndCombinator::Or(pattern)
     })
@@ -111,6 +250,8 @@ fn parse_and(input : &mut Chars) -> Result<EndCombinator, ParseError> {
     pat!(parse_dot: char => () = '.' => ());
     pat!(parse_l_paren: char => () = '(' => ());
     pat!(parse_r_paren: char => () = ')' => ());
+    expecting!(Unexpected, expect_l_paren = parse_l_paren : () => "`(`");
+    expecting!(UnbalancedBrackets, expect_r_paren = parse_r_paren : () => "`)`");
 
     fn parse_and(input : &mut Chars) -> Result<(), ParseError> {
         parser!(input => {
@@ -126,14 +267,43 @@ fn parse_and(input : &mut Chars) -> Result<EndCombinator, ParseError> {
         _ws1 <= parse_whitespace;
         _and <= parse_and;
         _ws2 <= parse_whitespace;
-        _paren_l <= ! parse_l_paren;
-        pattern <= ! parse_pattern;
-        _paren_r <= ! parse_r_paren; 
+        _paren_l <= ! expect_l_paren;
+        pattern <= ! expect_pattern;
+        _paren_r <= ! expect_r_paren; 
         _ws3 <= parse_whitespace;
         select EndCombinator::And(pattern)
     })
 }
 
+fn parse_not(input : &mut Chars) -> Result<EndCombinator, ParseError> {
+    pat!(parse_dot: char => () = '.' => ());
+    pat!(parse_l_paren: char => () = '(' => ());
+    pat!(parse_r_paren: char => () = ')' => ());
+    expecting!(Unexpected, expect_l_paren = parse_l_paren : () => "`(`");
+    expecting!(UnbalancedBrackets, expect_r_paren = parse_r_paren : () => "`)`");
+
+    fn parse_not(input : &mut Chars) -> Result<(), ParseError> {
+        parser!(input => {
+            not <= parse_word;
+            where *not == *"not";
+            select ()
+        })
+    }
+
+    parser!(input => {
+        _ws0 <= parse_whitespace;
+        _dot <= parse_dot;
+        _ws1 <= parse_whitespace;
+        _not <= parse_not;
+        _ws2 <= parse_whitespace;
+        _paren_l <= ! expect_l_paren;
+        pattern <= ! expect_pattern;
+        _paren_r <= ! expect_r_paren; 
+        _ws3 <= parse_whitespace;
+        select EndCombinator::Not(pattern)
+    })
+}
+
 fn parse_list_path(input : &mut Chars) -> Result<Pattern<SymStr>, ParseError> {
     pat!(parse_l_square: char => () = '[' => ());
     pat!(parse_r_square: char => () = ']' => ());
@@ -156,7 +326,7 @@ fn parse_list_path(input : &mut Chars) -> Result<Pattern<SymStr>, ParseError> {
     }
 
     fn parse_points(input : &mut Chars) -> Result<Vec<Pattern<SymStr>>, ParseError> {
-        parse_list!(input => parse_l_bracket, parse_pattern : Pattern<SymStr>, parse_r_bracket)
+        parse_list!(input => parse_l_bracket, parse_element : Pattern<SymStr>, parse_r_bracket = "`|]`")
     }
 
     parser!(input => {
@@ -187,7 +357,7 @@ fn parse_path(input : &mut Chars) -> Result<Pattern<SymStr>, ParseError> {
     }
 
     fn parse_points(input : &mut Chars) -> Result<Vec<Pattern<SymStr>>, ParseError> {
-        parse_list!(input => parse_l_bracket, parse_pattern : Pattern<SymStr>, parse_r_bracket)
+        parse_list!(input => parse_l_bracket, parse_pattern : Pattern<SymStr>, parse_r_bracket = "`|}`")
     }
 
     parser!(input => {
@@ -196,28 +366,147 @@ fn parse_path(input : &mut Chars) -> Result<Pattern<SymStr>, ParseError> {
     })
 }
 
+fn parse_object(input : &mut Chars) -> Result<Pattern<SymStr>, ParseError> {
+    pat!(parse_l_curl: char => () = '{' => ());
+    pat!(parse_r_curl: char => () = '}' => ());
+    pat!(parse_colon: char => () = ':' => ());
+
+    expecting!(Unexpected, expect_colon = parse_colon : () => "`:`");
+
+    fn parse_field(input : &mut Chars) -> Result<(Box<str>, Pattern<SymStr>), ParseError> {
+        parser!(input => {
+            key <= parse_name;
+            _clear <= parse_whitespace;
+            _colon <= ! expect_colon;
+            pattern <= ! expect_pattern;
+            select (key, pattern)
+        })
+    }
+
+    fn parse_fields(input : &mut Chars) -> Result<Vec<(Box<str>, Pattern<SymStr>)>, ParseError> {
+        parse_list!(input => parse_l_curl, parse_field : (Box<str>, Pattern<SymStr>), parse_r_curl = "`}`")
+    }
+
+    parser!(input => {
+        fields <= parse_fields;
+        select Pattern::Object(fields)
+    })
+}
+
 pat!(parse_path_next<'a>: char => Pattern<SymStr>= '^' => Pattern::PathNext);
 
+pat!(parse_fail<'a>: char => Pattern<SymStr>= '!' => Pattern::Fail);
+
 fn parse_cons(input : &mut Chars) -> Result<Pattern<SymStr>, ParseError> {
     pat!(parse_l_paren: char => () = '(' => ());
     pat!(parse_r_paren: char => () = ')' => ());
 
     fn param_list(input : &mut Chars) -> Result<Vec<Pattern<SymStr>>, ParseError> {
-        parse_list!(input => parse_l_paren, parse_pattern : Pattern<SymStr>, parse_r_paren)
+        parse_list!(input => parse_l_paren, parse_element : Pattern<SymStr>, parse_r_paren = "`)`")
     }
 
     parser!(input => {
-        cons_name <= parse_word;
+        cons_name <= parse_name;
         _clear <= parse_whitespace;
         params <= param_list;
         select Pattern::Cons { name: cons_name, params }
     })
 }
 
+fn parse_any_cons(input : &mut Chars) -> Result<Pattern<SymStr>, ParseError> {
+    pat!(parse_l_paren: char => () = '(' => ());
+    pat!(parse_r_paren: char => () = ')' => ());
+    pat!(parse_question: char => () = '?' => ());
+    pat!(parse_bar: char => () = '|' => ());
+    expecting!(ExpectedWord, expect_name = parse_name : Box<str> => "name");
+    expecting!(UnbalancedBrackets, expect_r_paren = parse_r_paren : () => "`|`", "`)`");
+    expecting!(Unexpected, expect_params = param_list : Vec<Pattern<SymStr>> => "`(`");
+
+    fn param_list(input : &mut Chars) -> Result<Vec<Pattern<SymStr>>, ParseError> {
+        parse_list!(input => parse_l_paren, parse_element : Pattern<SymStr>, parse_r_paren = "`)`")
+    }
+
+    fn wild(input : &mut Chars) -> Result<Pattern<SymStr>, ParseError> {
+        parser!(input => {
+            word <= parse_word;
+            where &*word == "_";
+            _clear <= parse_whitespace;
+            params <= param_list;
+            select Pattern::AnyCons { name: ConsName::Wild, params }
+        })
+    }
+
+    fn capture(input : &mut Chars) -> Result<Pattern<SymStr>, ParseError> {
+        parser!(input => {
+            _question <= parse_question;
+            name <= ! expect_name;
+            _clear <= parse_whitespace;
+            params <= ! expect_params;
+            select Pattern::AnyCons { name: ConsName::Capture(name), params }
+        })
+    }
+
+    fn bar_name(input : &mut Chars) -> Result<Box<str>, ParseError> {
+        parser!(input => {
+            _clear_0 <= parse_whitespace;
+            _bar <= parse_bar;
+            _clear_1 <= parse_whitespace;
+            name <= ! expect_name;
+            select name
+        })
+    }
+
+    // Note:  `(x)` is a group, so a choice of one name is only an any cons when the parameters follow
+    fn one_name(input : &mut Chars) -> Result<Pattern<SymStr>, ParseError> {
+        parser!(input => {
+            _paren_l <= parse_l_paren;
+            _clear_0 <= parse_whitespace;
+            name <= parse_name;
+            _clear_1 <= parse_whitespace;
+            _paren_r <= parse_r_paren;
+            _clear_2 <= parse_whitespace;
+            params <= param_list;
+            select Pattern::AnyCons { name: ConsName::OneOf(vec![name]), params }
+        })
+    }
+
+    fn one_of(input : &mut Chars) -> Result<Pattern<SymStr>, ParseError> {
+        parser!(input => {
+            _paren_l <= parse_l_paren;
+            _clear_0 <= parse_whitespace;
+            first <= parse_name;
+            names <= * bar_name;
+            where names.len() > 0;
+            _clear_1 <= parse_whitespace;
+            _paren_r <= ! expect_r_paren;
+            _clear_2 <= parse_whitespace;
+            params <= ! expect_params;
+            select {
+                let mut names = names;
+                names.insert(0, first);
+                Pattern::AnyCons { name: ConsName::OneOf(names), params }
+            }
+        })
+    }
+
+    alt!(input => wild; capture; one_name; one_of)
+}
+
+fn parse_rest(input : &mut Chars) -> Result<Pattern<SymStr>, ParseError> {
+    pat!(parse_dot: char => () = '.' => ());
+
+    parser!(input => {
+        _dot0 <= parse_dot;
+        _dot1 <= parse_dot;
+        name <= ? parse_name;
+        select Pattern::Rest(name)
+    })
+}
+
 fn parse_capture_var(input : &mut Chars) -> Result<Pattern<SymStr>, ParseError> {
     parser!(input => {
-        word <= parse_word;
-        select Pattern::CaptureVar(word)
+        name <= parse_name;
+        select Pattern::CaptureVar(name)
     })
 }
 
@@ -233,7 +522,7 @@ fn parse_symbol(input : &mut Chars) -> Result<Pattern<SymStr>, ParseError> {
     pat!(parse_colon: char => () = ':' => ());
     parser!(input => {
         _colon <= parse_colon;
-        word <= parse_word;
+        word <= parse_name;
         select Pattern::Atom(SymStr::Symbol(word))
     })
 }
@@ -245,20 +534,251 @@ fn parse_string_pattern(input : &mut Chars) -> Result<Pattern<SymStr>, ParseErro
     })
 }
 
+fn parse_number_pattern(input : &mut Chars) -> Result<Pattern<SymStr>, ParseError> {
+    parser!(input => {
+        number <= parse_number;
+        select Pattern::Atom(number)
+    })
+}
+
+fn parse_bound(input : &mut Chars) -> Result<SymStr, ParseError> {
+    pat!(parse_colon: char => () = ':' => ());
+
+    fn parse_bound_symbol(input : &mut Chars) -> Result<SymStr, ParseError> {
+        parser!(input => {
+            _colon <= parse_colon;
+            name <= parse_name;
+            select SymStr::Symbol(name)
+        })
+    }
+
+    fn parse_bound_string(input : &mut Chars) -> Result<SymStr, ParseError> {
+        parser!(input => {
+            string <= parse_string;
+            select SymStr::String(string)
+        })
+    }
+
+    alt!(input => parse_number; parse_bound_string; parse_bound_symbol)
+}
+
+// Note:  `low<..high` and `low<..=high` leave out their low end
+fn parse_range(input : &mut Chars) -> Result<Pattern<SymStr>, ParseError> {
+    pat!(parse_dot: char => () = '.' => ());
+    pat!(parse_equal: char => () = '=' => ());
+    pat!(parse_less: char => () = '<' => ());
+
+    parser!(input => {
+        low <= parse_bound;
+        _ws0 <= parse_whitespace;
+        exclusive <= ? parse_less;
+        _dot0 <= parse_dot;
+        _dot1 <= parse_dot;
+        inclusive <= ? parse_equal;
+        _ws1 <= parse_whitespace;
+        high <= ! expect_bound;
+        select {
+            let low = match exclusive {
+                Some(_) => Bound::Excluded(low),
+                None => Bound::Included(low),
+            };
+            match inclusive {
+                Some(_) => Pattern::Range(low, Bound::Included(high)),
+                None => Pattern::Range(low, Bound::Excluded(high)),
+            }
+        }
+    })
+}
+
+// Note:  a range without either end matches any atom, but it isn't the same pattern as `#atom`
+fn parse_unbounded_range(input : &mut Chars) -> Result<Pattern<SymStr>, ParseError> {
+    pat!(parse_hash: char => () = '#' => ());
+
+    parser!(input => {
+        _hash <= parse_hash;
+        word <= parse_word;
+        where &*word == "range";
+        select Pattern::Range(Bound::Unbounded, Bound::Unbounded)
+    })
+}
+
+fn parse_comparison(input : &mut Chars) -> Result<Pattern<SymStr>, ParseError> {
+    pat!(parse_equal: char => () = '=' => ());
+
+    fn parse_operator(input : &mut Chars) -> Result<(char, bool), ParseError> {
+        parser!(input => {
+            op <= parse_any;
+            where op == '<' || op == '>';
+            equal <= ? parse_equal;
+            select (op, equal.is_some())
+        })
+    }
+
+    parser!(input => {
+        op <= parse_operator;
+        _ws <= parse_whitespace;
+        bound <= ! expect_bound;
+        select match op {
+            ('<', false) => Pattern::Range(Bound::Unbounded, Bound::Excluded(bound)),
+            ('<', true) => Pattern::Range(Bound::Unbounded, Bound::Included(bound)),
+            ('>', false) => Pattern::Range(Bound::Excluded(bound), Bound::Unbounded),
+            _ => Pattern::Range(Bound::Included(bound), Bound::Unbounded),
+        }
+    })
+}
+
 fn parse_list(input : &mut Chars) -> Result<Pattern<SymStr>, ParseError> {
     pat!(parse_l_square: char => () = '[' => ());
     pat!(parse_r_square: char => () = ']' => ());
 
-    Ok(Pattern::ExactList(parse_list!(input => parse_l_square, parse_pattern : Pattern<SymStr>, parse_r_square)?))
+    Ok(Pattern::ExactList(parse_list!(input => parse_l_square, parse_element : Pattern<SymStr>, parse_r_square = "`]`")?))
+}
+
+fn parse_bag(input : &mut Chars) -> Result<Pattern<SymStr>, ParseError> {
+    pat!(parse_hash: char => () = '#' => ());
+    pat!(parse_l_square: char => () = '[' => ());
+    pat!(parse_r_square: char => () = ']' => ());
+
+    fn parse_l_bracket(input : &mut Chars) -> Result<(), ParseError> {
+        parser!(input => {
+            _hash <= parse_hash;
+            _square <= parse_l_square;
+            select ()
+        })
+    }
+
+    Ok(Pattern::Bag(parse_list!(input => parse_l_bracket, parse_pattern : Pattern<SymStr>, parse_r_square = "`]`")?))
+}
+
+fn parse_predicate(input : &mut Chars) -> Result<Pattern<SymStr>, ParseError> {
+    pat!(parse_hash: char => () = '#' => ());
+    pat!(parse_l_paren: char => () = '(' => ());
+    pat!(parse_r_paren: char => () = ')' => ());
+    expecting!(Unexpected, expect_l_paren = parse_l_paren : () => "`(`");
+    expecting!(UnbalancedBrackets, expect_r_paren = parse_r_paren : () => "`)`");
+    expecting!(Unexpected, expect_string = parse_string : Box<str> => "string");
+
+    fn parse_call(input : &mut Chars, name : &str) -> Result<(), ParseError> {
+        parser!(input => {
+            word <= parse_word;
+            where &*word == name;
+            _clear_0 <= parse_whitespace;
+            _paren_l <= ! expect_l_paren;
+            _clear_1 <= parse_whitespace;
+            select ()
+        })
+    }
+
+    fn parse_text(input : &mut Chars) -> Result<Box<str>, ParseError> {
+        parser!(input => {
+            text <= ! expect_string;
+            _clear <= parse_whitespace;
+            _paren_r <= ! expect_r_paren;
+            select text
+        })
+    }
+
+    fn prefix(input : &mut Chars) -> Result<StrPredicate, ParseError> {
+        parse_call(input, "prefix")?;
+        Ok(StrPredicate::Prefix(parse_text(input)?))
+    }
+
+    fn suffix(input : &mut Chars) -> Result<StrPredicate, ParseError> {
+        parse_call(input, "suffix")?;
+        Ok(StrPredicate::Suffix(parse_text(input)?))
+    }
+
+    fn contains(input : &mut Chars) -> Result<StrPredicate, ParseError> {
+        parse_call(input, "contains")?;
+        Ok(StrPredicate::Contains(parse_text(input)?))
+    }
+
+    // Note:  the regex is compiled here so that a bad one is reported where it was written
+    #[cfg(feature = "regex")]
+    fn regex(input : &mut Chars) -> Result<StrPredicate, ParseError> {
+        fn parse_regex(input : &mut Chars) -> Result<Regex, ParseError> {
+            let start = input.clone();
+            let source = parse_string(input)?;
+            match Regex::new(&source) {
+                Ok(regex) => Ok(regex),
+                Err(_) => {
+                    *input = start;
+                    Err(ParseError::Error)
+                },
+            }
+        }
+
+        expecting!(BadRegex, expect_regex = parse_regex : Regex => "valid regex");
+
+        parse_call(input, "regex")?;
+        parser!(input => {
+            regex <= ! expect_regex;
+            _clear <= parse_whitespace;
+            _paren_r <= ! expect_r_paren;
+            select StrPredicate::Regex(regex)
+        })
+    }
+
+    #[cfg(not(feature = "regex"))]
+    fn regex(input : &mut Chars) -> Result<StrPredicate, ParseError> {
+        fn disabled(_input : &mut Chars) -> Result<StrPredicate, ParseError> {
+            Err(ParseError::Error)
+        }
+
+        expecting!(FeatureDisabled, expect_enabled = disabled : StrPredicate => "the `regex` feature");
+
+        parse_call(input, "regex")?;
+        parser!(input => {
+            predicate <= ! expect_enabled;
+            select predicate
+        })
+    }
+
+    fn parse_kind(input : &mut Chars) -> Result<StrPredicate, ParseError> {
+        alt!(input => prefix; suffix; contains; regex)
+    }
+
+    parser!(input => {
+        _hash <= parse_hash;
+        predicate <= parse_kind;
+        select Pattern::Predicate(predicate)
+    })
+}
+
+fn parse_kind_test(input : &mut Chars) -> Result<Pattern<SymStr>, ParseError> {
+    pat!(parse_hash: char => () = '#' => ());
+
+    fn parse_kind(input : &mut Chars) -> Result<KindTest, ParseError> {
+        parser!(input => {
+            word <= parse_word;
+            where matches!(&*word, "symbol" | "string" | "number" | "atom" | "list" | "cons" | "object");
+            select match &*word {
+                "symbol" => KindTest::Symbol,
+                "string" => KindTest::String,
+                "number" => KindTest::Number,
+                "atom" => KindTest::Atom,
+                "list" => KindTest::List,
+                "cons" => KindTest::Cons,
+                _ => KindTest::Object,
+            }
+        })
+    }
+
+    parser!(input => {
+        _hash <= parse_hash;
+        kind <= parse_kind;
+        select Pattern::Kind(kind)
+    })
 }
 
 fn parse_template_variable(input : &mut Chars) -> Result<Pattern<SymStr>, ParseError> {
     pat!(parse_percent: char => () = '%' => ());
+    expecting!(ExpectedWord, expect_name = parse_name : Box<str> => "name");
 
     parser!(input => {
         _dollar <= parse_percent;
-        word <= ! parse_word;
-        select Pattern::TemplateVar(word)
+        name <= ! expect_name;
+        select Pattern::TemplateVar(name)
     })
 }
 
@@ -270,6 +790,54 @@ mod test {
     fn slice<'a, T>(input : &'a Vec<T>) -> &'a [T] { &input[..] }
     fn unbox<T>(input : Box<T> ) -> T { *input }
 
+    #[test]
+    fn should_report_error_position() {
+        fn t(input : &str, line : usize, column : usize, expected : Vec<&str>) {
+            let error = run_parser(input, parse, "pattern").unwrap_err();
+            let error = error.syntax();
+            assert_eq!((error.line, error.column), (line, column), "{input}");
+            assert_eq!(error.expected, expected.into_iter().map(|x| x.into()).collect::<Vec<Box<str>>>(), "{input}");
+        }
+
+        t("cons(a b)", 1, 8, vec!["`,`", "`)`"]);
+        t("[| a, b ]", 1, 9, vec!["`,`", "`|]`"]);
+        t("{| a\n , b", 2, 5, vec!["`,`", "`|}`"]);
+        t("a.and(\n\t[b)", 2, 4, vec!["`,`", "`]`"]);
+        t("a.or b", 1, 6, vec!["`(`"]);
+        t("a.not()", 1, 7, vec!["pattern"]);
+        t("{ a: b, c }", 1, 11, vec!["`:`"]);
+        t("1..#a", 1, 4, vec!["number", "string", "symbol"]);
+        t(">= x", 1, 4, vec!["number", "string", "symbol"]);
+        t("%1", 1, 2, vec!["name"]);
+        t("a b", 1, 3, vec!["end of input"]);
+        t("", 1, 1, vec!["pattern"]);
+    }
+
+    #[test]
+    fn should_report_error_kind() {
+        use crate::error::ParseError::*;
+
+        assert!(matches!("[\"abc]".parse::<Pattern<SymStr>>(), Err(UnterminatedString(_))));
+        assert!(matches!("\"\\w\"".parse::<Pattern<SymStr>>(), Err(BadEscape(_))));
+        assert!(matches!("%".parse::<Pattern<SymStr>>(), Err(ExpectedWord(_))));
+        assert!(matches!("[| a".parse::<Pattern<SymStr>>(), Err(UnbalancedBrackets(_))));
+        assert!(matches!("a.or(b".parse::<Pattern<SymStr>>(), Err(UnbalancedBrackets(_))));
+        assert!(matches!("a.and(b".parse::<Pattern<SymStr>>(), Err(UnbalancedBrackets(_))));
+        assert!(matches!("a.not(b".parse::<Pattern<SymStr>>(), Err(UnbalancedBrackets(_))));
+        assert!(matches!("(a".parse::<Pattern<SymStr>>(), Err(UnbalancedBrackets(_))));
+        assert!(matches!("a.or".parse::<Pattern<SymStr>>(), Err(Unexpected(_))));
+        assert!(matches!("a)".parse::<Pattern<SymStr>>(), Err(TrailingInput(_))));
+    }
+
+    #[test]
+    fn should_display_error_with_caret() {
+        let error = "cons(a,\n\t:b :c)".parse::<Pattern<SymStr>>().unwrap_err();
+        assert_eq!(format!("{}", error), "Unbalanced brackets: Expected `,` or `)` at line 2, column 5\n\t:b :c)\n\t   ^");
+
+        let error = "[%]".parse::<Pattern<SymStr>>().unwrap_err();
+        assert_eq!(format!("{}", error), "Expected word: Expected name at line 1, column 3\n[%]\n  ^");
+    }
+
     #[test]
     fn should_parse_template() {
         let input = "cons(%a, [%b, %c, d, :e] )";
@@ -337,6 +905,51 @@ mod test {
         assert!(matched);
     }
 
+    #[test]
+    fn should_parse_not() {
+        let input = ":a . not ( \"1.0\" )";
+        let pattern = input.parse::<Pattern<SymStr>>().unwrap();
+        let mut matched = false;
+        atom!(pattern => [Pattern::And(a, b)] b; unbox $ [Pattern::Not(c)] =>  {
+            assert!( matches!( *a, Pattern::Atom(SymStr::Symbol(_))) );
+            assert!( matches!( *c, Pattern::Atom(SymStr::String(_))) );
+            matched = true;
+        } );
+        assert!(matched);
+    }
+
+    #[test]
+    fn should_parse_not_after_wild() {
+        let input = "_.not(:null)";
+        let pattern = input.parse::<Pattern<SymStr>>().unwrap();
+        let mut matched = false;
+        atom!(pattern => [Pattern::Not(a)] =>  {
+            assert!( matches!( *a, Pattern::Atom(SymStr::Symbol(_))) );
+            matched = true;
+        } );
+        assert!(matched);
+    }
+
+    #[test]
+    fn should_parse_fail() {
+        let input = " ! ";
+        let pattern = input.parse::<Pattern<SymStr>>().unwrap();
+        assert!(matches!(pattern, Pattern::Fail));
+    }
+
+    #[test]
+    fn should_parse_or_with_fail() {
+        let input = "cons(a).or(!)";
+        let pattern = input.parse::<Pattern<SymStr>>().unwrap();
+        let mut matched = false;
+        atom!(pattern => [Pattern::Or(a, b)] =>  {
+            assert!( matches!( *a, Pattern::Cons { .. }) );
+            assert!( matches!( *b, Pattern::Fail) );
+            matched = true;
+        } );
+        assert!(matched);
+    }
+
     #[test]
     fn should_parse_path() {
         let input = "{| a, b, c |}";
@@ -344,6 +957,31 @@ mod test {
         assert!(matches!(pattern, Pattern::Path(_)));
     }
 
+    #[test]
+    fn should_parse_object() {
+        let input = "{ name : n, age: _, kind: :person }";
+        let pattern = input.parse::<Pattern<SymStr>>().unwrap();
+
+        let mut matched = false;
+        atom!(pattern => [Pattern::Object(ref fields)] fields;
+              slice $ [ [(name, Pattern::CaptureVar(n)), (age, Pattern::Wild), (kind, Pattern::Atom(SymStr::Symbol(person)))] ] => {
+            assert_eq!(**name, *"name");
+            assert_eq!(**n, *"n");
+            assert_eq!(**age, *"age");
+            assert_eq!(**kind, *"kind");
+            assert_eq!(**person, *"person");
+            matched = true;
+        } );
+        assert!(matched);
+    }
+
+    #[test]
+    fn should_parse_empty_object() {
+        let input = "{ }";
+        let pattern = input.parse::<Pattern<SymStr>>().unwrap();
+        assert!(matches!(pattern, Pattern::Object(fields) if fields.len() == 0));
+    }
+
     #[test]
     fn should_parse_list_path() {
         let input = "[| a, b, c |]";
@@ -382,6 +1020,200 @@ mod test {
         assert!(matched);
     }
 
+    #[test]
+    fn should_parse_numbers() {
+        let input = " cons( 5, -3, 5.5, -1.5e2 )";
+        let pattern = input.parse::<Pattern<SymStr>>().unwrap();
+
+        let mut matched = false;
+        atom!(pattern => [Pattern::Cons { ref params, .. }] params; 
+                       slice $ [ [ Pattern::Atom(SymStr::Int(a))
+                                 , Pattern::Atom(SymStr::Int(b))
+                                 , Pattern::Atom(SymStr::Float(c))
+                                 , Pattern::Atom(SymStr::Float(d))
+                                 ] ] => { 
+            assert_eq!(*a, 5);
+            assert_eq!(*b, -3);
+            assert_eq!(*c, 5.5);
+            assert_eq!(*d, -150.0);
+            matched = true;
+        } );
+        assert!(matched);
+    }
+
+    #[test]
+    fn should_parse_range() {
+        let input = " 1 .. 10 ";
+        let pattern = input.parse::<Pattern<SymStr>>().unwrap();
+        assert!(matches!(pattern, Pattern::Range(Bound::Included(SymStr::Int(1)), Bound::Excluded(SymStr::Int(10)))));
+    }
+
+    #[test]
+    fn should_parse_inclusive_range() {
+        let input = "-1.5..=2";
+        let pattern = input.parse::<Pattern<SymStr>>().unwrap();
+        assert!(matches!(pattern, Pattern::Range(Bound::Included(SymStr::Float(_)), Bound::Included(SymStr::Int(2)))));
+    }
+
+    #[test]
+    fn should_parse_range_with_exclusive_low() {
+        let input = "1<..=2";
+        let pattern = input.parse::<Pattern<SymStr>>().unwrap();
+        assert!(matches!(pattern, Pattern::Range(Bound::Excluded(SymStr::Int(1)), Bound::Included(SymStr::Int(2)))));
+    }
+
+    #[test]
+    fn should_parse_range_of_strings_and_symbols() {
+        let input = "\"a\"..:b";
+        let pattern = input.parse::<Pattern<SymStr>>().unwrap();
+        assert_eq!(pattern, Pattern::Range(Bound::Included(SymStr::String("a".into())), Bound::Excluded(SymStr::Symbol("b".into()))));
+    }
+
+    #[test]
+    fn should_display_every_range_so_it_parses_back() {
+        fn t(pattern : Pattern<SymStr>) {
+            let compact = pattern.to_string();
+            let pretty = format!("{:#}", pattern);
+            assert_eq!(compact.parse::<Pattern<SymStr>>().unwrap(), pattern, "{compact}");
+            assert_eq!(pretty.parse::<Pattern<SymStr>>().unwrap(), pattern, "{pretty}");
+        }
+
+        let bounds = [ SymStr::Int(-1)
+                     , SymStr::Float(2.5)
+                     , SymStr::String("a b".into())
+                     , SymStr::Symbol("c".into())
+                     , SymStr::Symbol("d e".into())
+                     ];
+
+        let mut ends = vec![Bound::Unbounded];
+        for b in bounds {
+            ends.push(Bound::Included(b.clone()));
+            ends.push(Bound::Excluded(b));
+        }
+
+        for low in &ends {
+            for high in &ends {
+                let range = Pattern::Range(low.clone(), high.clone());
+                t(range.clone());
+                t(Pattern::Descendant(Box::new(range.clone())));
+                t(Pattern::ExactList(vec![range.clone(), Pattern::Wild]));
+                t(Pattern::As("x".into(), Box::new(range)));
+            }
+        }
+    }
+
+    #[test]
+    fn should_display_names_that_are_not_words_so_they_parse_back() {
+        fn t(pattern : Pattern<SymStr>) {
+            let output = pattern.to_string();
+            assert_eq!(output.parse::<Pattern<SymStr>>().unwrap(), pattern, "{output}");
+        }
+
+        for name in ["_", "a b", "1x", "`", "\\", "x"] {
+            t(Pattern::CaptureVar(name.into()));
+            t(Pattern::ExactList(vec![Pattern::CaptureVar(name.into()), Pattern::Rest(Some(name.into()))]));
+            t(Pattern::As(name.into(), Box::new(Pattern::Wild)));
+            t(Pattern::TemplateVar(name.into()));
+            t(Pattern::Cons { name: name.into(), params: vec![Pattern::CaptureVar(name.into())] });
+            t(Pattern::AnyCons { name: ConsName::Capture(name.into()), params: vec![] });
+            t(Pattern::Object(vec![(name.into(), Pattern::TemplateVar(name.into()))]));
+        }
+    }
+
+    #[test]
+    fn should_parse_comparisons() {
+        fn t(input : &str, low : Bound<SymStr>, high : Bound<SymStr>) {
+            let pattern = input.parse::<Pattern<SymStr>>().unwrap();
+            match pattern {
+                Pattern::Range(l, h) => { assert_eq!(l, low, "{input}"); assert_eq!(h, high, "{input}"); },
+                _ => panic!("{input} did not parse to range"),
+            }
+        }
+
+        t("< 0.5", Bound::Unbounded, Bound::Excluded(SymStr::Float(0.5)));
+        t("<= 3", Bound::Unbounded, Bound::Included(SymStr::Int(3)));
+        t("> -3", Bound::Excluded(SymStr::Int(-3)), Bound::Unbounded);
+        t(">=3", Bound::Included(SymStr::Int(3)), Bound::Unbounded);
+    }
+
+    #[test]
+    fn should_parse_rest() {
+        let input = "[a, .., b, ..rest]";
+        let pattern = input.parse::<Pattern<SymStr>>().unwrap();
+
+        let mut matched = false;
+        atom!(pattern => [Pattern::ExactList(ref params)] params; 
+              slice $ [ [Pattern::CaptureVar(_), Pattern::Rest(None), Pattern::CaptureVar(_), Pattern::Rest(Some(rest))] ] => { 
+            assert_eq!(**rest, *"rest");
+            matched = true;
+        } );
+        assert!(matched);
+    }
+
+    #[test]
+    fn should_display_pattern() {
+        fn t(input : &str, expected : &str) {
+            let pattern = input.parse::<Pattern<SymStr>>().unwrap();
+            assert_eq!(format!("{}", pattern), expected, "{input}");
+        }
+
+        t(" cons ( a , :b , \"c\" , 1 , -2.5 ) ", "cons(a, :b, \"c\", 1, -2.5)");
+        t("[ _ , ! , ^ , .. , ..rest , %a ]", "[_, !, ^, .., ..rest, %a]");
+        t("{| [| a |], { k : v } |}", "{|[|a|], {k: v}|}");
+        t("1 .. 2", "1..2");
+        t(">=3", ">= 3");
+        t("a . and ( b ) . or ( c )", "a.and(b).or(c)");
+        t("a.not(b)", "a.not(b)");
+        t("_.not(b)", "_.not(b)");
+        t("_.and(_.not(b))", "_.and(_.not(b))");
+    }
+
+    #[test]
+    fn should_round_trip_display() {
+        fn t(input : &str) {
+            let pattern = input.parse::<Pattern<SymStr>>().unwrap();
+            let compact = format!("{}", pattern);
+            let pretty = format!("{:#}", pattern);
+            assert_eq!(compact.parse::<Pattern<SymStr>>().unwrap(), pattern, "{input} => {compact}");
+            assert_eq!(pretty.parse::<Pattern<SymStr>>().unwrap(), pattern, "{input} => {pretty}");
+        }
+
+        t("_");
+        t("!");
+        t("x");
+        t(":symbol");
+        t("\"string\"");
+        t("5");
+        t("-5.5e-3");
+        t("[]");
+        t("[| |]");
+        t("{| |}");
+        t("{}");
+        t("cons()");
+        t("cons(a, ..rest, [:b, \"c\"], %a)");
+        t("[| a, b |].and( [| c, :five, d |] )");
+        t("[x, :nine, :nine].or( [:zero, x, :two].or([:nine, :nine, x]) )");
+        t("{| cons( {| inner(^, ^), inner(:one, a, b) |} , ^, ^), outer(:one, c, d) |}");
+        t("{ name: n, age: >= 18, tags: [.., :admin] }");
+        t("[1..10, 1..=10, 1<..10, 1<..=10, < 0.5, <= 1, > -1, >= 2, #range]");
+        t("[:a..:b, \"a\"..=\"b\", < :z, >= \"y\"]");
+        t("call(a, _.not(:null)).or(!)");
+        t("[| a.not(cons(..)) |]");
+        t("_.not(a).and(b)");
+        t("_.and(_.not(a))");
+        t("a.and(b).not(c).and(_.not(d))");
+        t("[a.or(b.and(c)), a.and(b.or(c))]");
+        t("_.not(_.not({ a: [cons(1, 2)] }))");
+    }
+
+    #[test]
+    fn should_pretty_print_pattern() {
+        let input = "cons(a, [b, c], {| [^, ^], x |}.or(!))";
+        let pattern = input.parse::<Pattern<SymStr>>().unwrap();
+        let expected = "cons(\n    a,\n    [b, c],\n    {|\n        [^, ^],\n        x\n    |}\n    .or(!)\n)";
+        assert_eq!(format!("{:#}", pattern), expected);
+    }
+
     #[test]
     fn should_parse_capture_var() {
         let input = " symbol_123 ";
@@ -449,4 +1281,407 @@ mod test {
 
         assert!(matched);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn should_parse_comments() {
+        let input = "/* header */ cons( a // first
+                                   , [b, /* c, */ d]
+                                   , {| e, f, |} // path
+                                   ).or( // nothing
+                                   :g )";
+        let pattern = input.parse::<Pattern<SymStr>>().unwrap();
+        assert_eq!(pattern, "cons(a, [b, d], {| e, f |}).or(:g)".parse::<Pattern<SymStr>>().unwrap());
+    }
+
+    #[test]
+    fn should_allow_single_trailing_comma() {
+        fn t(input : &str, expected : &str) {
+            assert_eq!(input.parse::<Pattern<SymStr>>().unwrap(), expected.parse::<Pattern<SymStr>>().unwrap(), "{input}");
+        }
+
+        t("[a, b,]", "[a, b]");
+        t("cons(a,)", "cons(a)");
+        t("[| a, b, |]", "[| a, b |]");
+        t("{ a: b, }", "{ a: b }");
+        assert!("cons(,)".parse::<Pattern<SymStr>>().is_err());
+        assert!("[a,,]".parse::<Pattern<SymStr>>().is_err());
+    }
+
+    #[test]
+    fn should_parse_quoted_names() {
+        let input = "`foo-bar`(:`a.b`, x, {`a key`: y})";
+        let pattern = input.parse::<Pattern<SymStr>>().unwrap();
+        let expected = Pattern::Cons { name: "foo-bar".into()
+                                     , params: vec![ Pattern::Atom(SymStr::Symbol("a.b".into()))
+                                                   , Pattern::CaptureVar("x".into())
+                                                   , Pattern::Object(vec![("a key".into(), Pattern::CaptureVar("y".into()))])
+                                                   ]
+                                     };
+        assert_eq!(pattern, expected);
+        assert_eq!(format!("{}", pattern), input);
+        assert_eq!(format!("{}", pattern).parse::<Pattern<SymStr>>().unwrap(), pattern);
+    }
+
+    #[test]
+    fn should_parse_as_pattern() {
+        fn t(input : &str, expected : Pattern<SymStr>) {
+            let pattern = input.parse::<Pattern<SymStr>>().unwrap();
+            assert_eq!(pattern, expected, "{input}");
+        }
+
+        fn cap(name : &str) -> Pattern<SymStr> { Pattern::CaptureVar(name.into()) }
+        fn as_p(name : &str, p : Pattern<SymStr>) -> Pattern<SymStr> { Pattern::As(name.into(), Box::new(p)) }
+        fn cons(ps : Vec<Pattern<SymStr>>) -> Pattern<SymStr> { Pattern::Cons { name: "cons".into(), params: ps } }
+
+        t("b @ cons(x, y)", as_p("b", cons(vec![cap("x"), cap("y")])));
+        t("b@cons(x, y)", as_p("b", cons(vec![cap("x"), cap("y")])));
+        t("[a @ _, b @ c @ :d]", Pattern::ExactList(vec![as_p("a", Pattern::Wild), as_p("b", as_p("c", Pattern::Atom(SymStr::Symbol("d".into()))))]));
+        t("b @ cons(x).or(y)", Pattern::Or(Box::new(as_p("b", cons(vec![cap("x")]))), Box::new(cap("y"))));
+        t("b @ (cons(x).or(x))", as_p("b", Pattern::Or(Box::new(cons(vec![cap("x")])), Box::new(cap("x")))));
+        t("(x)", cap("x"));
+    }
+
+    #[test]
+    fn should_fail_as_pattern() {
+        assert!("_ @ x".parse::<Pattern<SymStr>>().is_err());
+        assert!("x @".parse::<Pattern<SymStr>>().is_err());
+        assert!("x @ (y".parse::<Pattern<SymStr>>().is_err());
+    }
+
+    #[test]
+    fn should_display_as_pattern_so_it_parses_back() {
+        fn t(input : &str) {
+            let pattern = input.parse::<Pattern<SymStr>>().unwrap();
+            let output = format!("{}", pattern);
+            assert_eq!(output, input);
+            assert_eq!(output.parse::<Pattern<SymStr>>().unwrap(), pattern);
+        }
+
+        t("b @ cons(x, y)");
+        t("b @ cons(x).or(y)");
+        t("b @ (cons(x).or(y))");
+        t("[a @ b @ _, c @ (_.not(:d))]");
+    }
+
+    #[test]
+    fn should_parse_descendant_pattern() {
+        fn t(input : &str, expected : Pattern<SymStr>) {
+            let pattern = input.parse::<Pattern<SymStr>>().unwrap();
+            assert_eq!(pattern, expected, "{input}");
+        }
+
+        fn cap(name : &str) -> Pattern<SymStr> { Pattern::CaptureVar(name.into()) }
+        fn deep(p : Pattern<SymStr>) -> Pattern<SymStr> { Pattern::Descendant(Box::new(p)) }
+        fn cons(ps : Vec<Pattern<SymStr>>) -> Pattern<SymStr> { Pattern::Cons { name: "cons".into(), params: ps } }
+
+        t("**cons(x)", deep(cons(vec![cap("x")])));
+        t("** cons(x)", deep(cons(vec![cap("x")])));
+        t("[**x, ** **y]", Pattern::ExactList(vec![deep(cap("x")), deep(deep(cap("y")))]));
+        t("**cons(x).or(y)", Pattern::Or(Box::new(deep(cons(vec![cap("x")]))), Box::new(cap("y"))));
+        t("**(cons(x).or(x))", deep(Pattern::Or(Box::new(cons(vec![cap("x")])), Box::new(cap("x")))));
+        t("b @ **x", Pattern::As("b".into(), Box::new(deep(cap("x")))));
+    }
+
+    #[test]
+    fn should_fail_descendant_pattern() {
+        assert!("**".parse::<Pattern<SymStr>>().is_err());
+        assert!("* x".parse::<Pattern<SymStr>>().is_err());
+        assert!("**(x".parse::<Pattern<SymStr>>().is_err());
+    }
+
+    #[test]
+    fn should_display_descendant_pattern_so_it_parses_back() {
+        fn t(input : &str) {
+            let pattern = input.parse::<Pattern<SymStr>>().unwrap();
+            let output = format!("{}", pattern);
+            assert_eq!(output, input);
+            assert_eq!(output.parse::<Pattern<SymStr>>().unwrap(), pattern);
+        }
+
+        t("**cons(x, y)");
+        t("**cons(x).or(y)");
+        t("**(cons(x).or(y))");
+        t("[**b @ _, **(_.not(:d))]");
+    }
+
+    #[test]
+    fn should_parse_bag_pattern() {
+        fn t(input : &str, expected : Pattern<SymStr>) {
+            let pattern = input.parse::<Pattern<SymStr>>().unwrap();
+            assert_eq!(pattern, expected, "{input}");
+        }
+
+        fn cap(name : &str) -> Pattern<SymStr> { Pattern::CaptureVar(name.into()) }
+        fn sym(name : &str) -> Pattern<SymStr> { Pattern::Atom(SymStr::Symbol(name.into())) }
+
+        t("#[]", Pattern::Bag(vec![]));
+        t("#[x, :a]", Pattern::Bag(vec![cap("x"), sym("a")]));
+        t("#[ x , :a , ]", Pattern::Bag(vec![cap("x"), sym("a")]));
+        t("#[:a, ..rest]", Pattern::Bag(vec![sym("a"), Pattern::Rest(Some("rest".into()))]));
+        t("#[#[x], [y]]", Pattern::Bag(vec![Pattern::Bag(vec![cap("x")]), Pattern::ExactList(vec![cap("y")])]));
+    }
+
+    #[test]
+    fn should_fail_bag_pattern() {
+        assert!("#[x".parse::<Pattern<SymStr>>().is_err());
+        assert!("# [x]".parse::<Pattern<SymStr>>().is_err());
+        assert!("#[x*]".parse::<Pattern<SymStr>>().is_err());
+    }
+
+    #[test]
+    fn should_display_bag_pattern_so_it_parses_back() {
+        fn t(input : &str) {
+            let pattern = input.parse::<Pattern<SymStr>>().unwrap();
+            let output = format!("{}", pattern);
+            assert_eq!(output, input);
+            assert_eq!(output.parse::<Pattern<SymStr>>().unwrap(), pattern);
+        }
+
+        t("#[]");
+        t("#[x, :a, ..rest]");
+        t("[#[x], #[..]]");
+    }
+
+    #[test]
+    fn should_parse_any_cons_pattern() {
+        fn t(input : &str, expected : Pattern<SymStr>) {
+            let pattern = input.parse::<Pattern<SymStr>>().unwrap();
+            assert_eq!(pattern, expected, "{input}");
+        }
+
+        fn cap(name : &str) -> Pattern<SymStr> { Pattern::CaptureVar(name.into()) }
+        fn any(name : ConsName, ps : Vec<Pattern<SymStr>>) -> Pattern<SymStr> { Pattern::AnyCons { name, params: ps } }
+
+        t("_(a, b)", any(ConsName::Wild, vec![cap("a"), cap("b")]));
+        t("_ ()", any(ConsName::Wild, vec![]));
+        t("?n(a)", any(ConsName::Capture("n".into()), vec![cap("a")]));
+        t("(add|sub)(a, b)", any(ConsName::OneOf(vec!["add".into(), "sub".into()]), vec![cap("a"), cap("b")]));
+        t("( add | `sub-2` | mul )(a)", any(ConsName::OneOf(vec!["add".into(), "sub-2".into(), "mul".into()]), vec![cap("a")]));
+        t("`_`(a)", Pattern::Cons { name: "_".into(), params: vec![cap("a")] });
+        t("(a)", cap("a"));
+        t("_", Pattern::Wild);
+        t("x @ _(y)", Pattern::As("x".into(), Box::new(any(ConsName::Wild, vec![cap("y")]))));
+    }
+
+    #[test]
+    fn should_fail_any_cons_pattern() {
+        assert!("?n".parse::<Pattern<SymStr>>().is_err());
+        assert!("?(a)".parse::<Pattern<SymStr>>().is_err());
+        assert!("(add|)(a)".parse::<Pattern<SymStr>>().is_err());
+        assert!("(add|sub(a)".parse::<Pattern<SymStr>>().is_err());
+        assert!("(add|sub)".parse::<Pattern<SymStr>>().is_err());
+    }
+
+    #[test]
+    fn should_display_any_cons_pattern_so_it_parses_back() {
+        fn t(input : &str) {
+            let pattern = input.parse::<Pattern<SymStr>>().unwrap();
+            let output = format!("{}", pattern);
+            assert_eq!(output, input);
+            assert_eq!(output.parse::<Pattern<SymStr>>().unwrap(), pattern);
+        }
+
+        t("_(a, b)");
+        t("?n()");
+        t("(add|`sub-2`)(a, ..)");
+        t("`_`(a)");
+    }
+
+    #[test]
+    fn should_parse_single_name_any_cons() {
+        fn t(input : &str, name : &str) {
+            let pattern = input.parse::<Pattern<SymStr>>().unwrap();
+            assert_eq!(pattern, Pattern::AnyCons { name: ConsName::OneOf(vec![name.into()]), params: vec![Pattern::CaptureVar("a".into())] }, "{input}");
+        }
+
+        t("(add)(a)", "add");
+        t("( `sub-2` ) (a)", "sub-2");
+        t("(_)(a)", "_");
+    }
+
+    #[test]
+    fn should_still_parse_group_of_one_name() {
+        let pattern = "[(a), (b).or(c)]".parse::<Pattern<SymStr>>().unwrap();
+        assert_eq!(pattern, Pattern::ExactList(vec![ Pattern::CaptureVar("a".into())
+                                                   , Pattern::Or(Box::new(Pattern::CaptureVar("b".into())), Box::new(Pattern::CaptureVar("c".into())))
+                                                   ]));
+    }
+
+    #[test]
+    fn should_display_single_name_any_cons_so_it_parses_back() {
+        fn t(name : &str, expected : &str) {
+            let pattern : Pattern<SymStr> = Pattern::AnyCons { name: ConsName::OneOf(vec![name.into()]), params: vec![Pattern::CaptureVar("a".into())] };
+            let output = format!("{}", pattern);
+            assert_eq!(output, expected);
+            assert_eq!(output.parse::<Pattern<SymStr>>().unwrap(), pattern);
+        }
+
+        t("add", "(add)(a)");
+        t("_", "(_)(a)");
+        t("sub-2", "(`sub-2`)(a)");
+    }
+
+    #[test]
+    fn should_parse_string_predicate() {
+        fn t(input : &str, expected : StrPredicate) {
+            let pattern = input.parse::<Pattern<SymStr>>().unwrap();
+            assert_eq!(pattern, Pattern::Predicate(expected), "{input}");
+        }
+
+        t("#prefix(\"test_\")", StrPredicate::Prefix("test_".into()));
+        t("#suffix( \".rs\" )", StrPredicate::Suffix(".rs".into()));
+        t("#contains (\"\\n\")", StrPredicate::Contains("\n".into()));
+    }
+
+    #[test]
+    fn should_fail_string_predicate() {
+        use crate::error::ParseError::*;
+
+        assert!(matches!("#prefix".parse::<Pattern<SymStr>>(), Err(Unexpected(_))));
+        assert!(matches!("#prefix(:a)".parse::<Pattern<SymStr>>(), Err(Unexpected(_))));
+        assert!(matches!("#suffix(\"a\"".parse::<Pattern<SymStr>>(), Err(UnbalancedBrackets(_))));
+        assert!("#other(\"a\")".parse::<Pattern<SymStr>>().is_err());
+        assert!("prefix(\"a\")".parse::<Pattern<SymStr>>().is_ok());
+    }
+
+    #[test]
+    fn should_display_string_predicate_so_it_parses_back() {
+        fn t(input : &str) {
+            let pattern = input.parse::<Pattern<SymStr>>().unwrap();
+            let output = format!("{}", pattern);
+            assert_eq!(output, input);
+            assert_eq!(output.parse::<Pattern<SymStr>>().unwrap(), pattern);
+        }
+
+        t("#prefix(\"test_\")");
+        t("[x @ #suffix(\"\\\"\"), #contains(\"a b\").not(:c)]");
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn should_parse_regex_predicate() {
+        let pattern = "#regex(\"^v\\\\d+$\")".parse::<Pattern<SymStr>>().unwrap();
+        assert_eq!(pattern, Pattern::Predicate(StrPredicate::Regex(Regex::new("^v\\d+$").unwrap())));
+        assert_eq!(format!("{}", pattern), "#regex(\"^v\\\\d+$\")");
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn should_report_bad_regex() {
+        let error = "[#regex(\"(\")]".parse::<Pattern<SymStr>>().unwrap_err();
+        assert!(matches!(error, crate::error::ParseError::BadRegex(_)));
+        assert_eq!(error.syntax().column, 9);
+    }
+
+    #[cfg(not(feature = "regex"))]
+    #[test]
+    fn should_report_regex_without_feature() {
+        let error = "#regex(\"a\")".parse::<Pattern<SymStr>>().unwrap_err();
+        assert!(matches!(error, crate::error::ParseError::FeatureDisabled(_)));
+        assert!(error.to_string().starts_with("Feature disabled: Expected the `regex` feature"), "{error}");
+    }
+
+    #[test]
+    fn should_parse_kind_test() {
+        fn t(input : &str, expected : KindTest) {
+            let pattern = input.parse::<Pattern<SymStr>>().unwrap();
+            assert_eq!(pattern, Pattern::Kind(expected), "{input}");
+        }
+
+        t("#symbol", KindTest::Symbol);
+        t("#string", KindTest::String);
+        t("#number", KindTest::Number);
+        t("#atom", KindTest::Atom);
+        t("#list", KindTest::List);
+        t("#cons", KindTest::Cons);
+        t("#object", KindTest::Object);
+    }
+
+    #[test]
+    fn should_parse_symbols_that_look_like_kind_tests() {
+        fn t(input : &str, expected : &str) {
+            let pattern = input.parse::<Pattern<SymStr>>().unwrap();
+            assert_eq!(pattern, Pattern::Atom(SymStr::Symbol(expected.into())), "{input}");
+        }
+
+        t(":_", "_");
+        t(":`_`", "_");
+        t(":_a", "_a");
+        t(":list", "list");
+        assert_eq!("\"_\"".parse::<Pattern<SymStr>>().unwrap(), Pattern::Atom(SymStr::String("_".into())));
+        assert!("#lists".parse::<Pattern<SymStr>>().is_err());
+    }
+
+    #[test]
+    fn should_display_kind_test_so_it_parses_back() {
+        fn t(input : &str, expected : &str) {
+            let pattern = input.parse::<Pattern<SymStr>>().unwrap();
+            let output = format!("{}", pattern);
+            assert_eq!(output, expected);
+            assert_eq!(output.parse::<Pattern<SymStr>>().unwrap(), pattern);
+        }
+
+        t(":_", ":_");
+        t("#symbol", "#symbol");
+        t(":`_`", ":_");
+        t("[x.and(#list), y @ #string, #cons.or(#object)]", "[x.and(#list), y @ #string, #cons.or(#object)]");
+    }
+
+    #[test]
+    fn should_parse_repetition() {
+        fn t(input : &str, min : usize, max : Option<usize>) {
+            let pattern = input.parse::<Pattern<SymStr>>().unwrap();
+            let expected = Pattern::ExactList(vec![ Pattern::Atom(SymStr::Symbol("a".into()))
+                                                  , Pattern::Repeat { pattern: Box::new(Pattern::CaptureVar("x".into())), min, max }
+                                                  ]);
+            assert_eq!(pattern, expected, "{input}");
+        }
+
+        t("[:a, x*]", 0, None);
+        t("[:a, x+]", 1, None);
+        t("[:a, x?]", 0, Some(1));
+        t("[:a, x{3}]", 3, Some(3));
+        t("[:a, x{2,}]", 2, None);
+        t("[:a, x{2,5}]", 2, Some(5));
+        t("[:a, x { 2 , 5 } ]", 2, Some(5));
+        t("[:a, x *]", 0, None);
+    }
+
+    #[test]
+    fn should_parse_repetition_in_cons_and_list_path() {
+        let pattern = "cons(a.or(b)*, [| x+ |])".parse::<Pattern<SymStr>>().unwrap();
+        let expected = Pattern::Cons { name: "cons".into()
+                                     , params: vec![ Pattern::Repeat { pattern: Box::new(Pattern::Or(Box::new(Pattern::CaptureVar("a".into())), Box::new(Pattern::CaptureVar("b".into())))), min: 0, max: None }
+                                                   , Pattern::ListPath(vec![Pattern::Repeat { pattern: Box::new(Pattern::CaptureVar("x".into())), min: 1, max: None }])
+                                                   ]
+                                     };
+        assert_eq!(pattern, expected);
+    }
+
+    #[test]
+    fn should_fail_repetition() {
+        fn t(input : &str) {
+            assert!(input.parse::<Pattern<SymStr>>().is_err(), "{input}");
+        }
+
+        t("x*");
+        t("{ a: x* }");
+        t("[x{}]");
+        t("[x{2,a}]");
+        t("[x{2]");
+        t("[x**]");
+    }
+
+    #[test]
+    fn should_display_repetition_so_it_parses_back() {
+        fn t(input : &str) {
+            let pattern = input.parse::<Pattern<SymStr>>().unwrap();
+            let output = format!("{}", pattern);
+            assert_eq!(output, input);
+            assert_eq!(output.parse::<Pattern<SymStr>>().unwrap(), pattern);
+        }
+
+        t("[a*, b+, c?, d{2}, e{2,}, f{2,5}]");
+        t("cons(x.or(y)*, [|z @ [_+]|])");
+    }
+}